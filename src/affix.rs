@@ -0,0 +1,186 @@
+use item::{Equipment, Slot};
+use object::*;
+use data::GameData;
+use spawn::{Rarity, SpawnRule, SpawnTable, Spawnable};
+use colors;
+
+use rand::Rng;
+use random;
+
+/// Chances of a placed piece of equipment being magic, by rarity tier.
+const UNCOMMON_CHANCE: f32 = 0.15;
+const RARE_CHANCE: f32 = 0.05;
+const VERY_RARE_CHANCE: f32 = 0.01;
+/// Very rare items get this many times the bonuses of their affixes.
+const VERY_RARE_STRENGTH: i32 = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum AffixKind {
+    /// Named before the item, like "sharp".
+    Prefix,
+    /// Named after the item, like "of fire".
+    Suffix,
+}
+
+/// Whether an affix belongs on weapons, on armour or on either.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum AffixTarget {
+    Any,
+    Weapons,
+    Armour,
+}
+
+impl Default for AffixTarget {
+    fn default() -> Self {
+        AffixTarget::Any
+    }
+}
+
+/// Extra damage dealt whenever a melee attack lands.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct OnHit {
+    pub damage_type: DamageType,
+    pub damage: i32,
+}
+
+/// A bonus that magic equipment can be generated with, as defined in the data files.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AffixTemplate {
+    pub name: String,
+    pub kind: AffixKind,
+    /// Slots the affix can appear on. Empty means any slot.
+    #[serde(default)]
+    pub slots: Vec<Slot>,
+    #[serde(default)]
+    pub target: AffixTarget,
+    #[serde(default)]
+    pub power_bonus: i32,
+    #[serde(default)]
+    pub defense_bonus: i32,
+    #[serde(default)]
+    pub max_hp_bonus: i32,
+    #[serde(default)]
+    pub resistances: Resistances,
+    #[serde(default)]
+    pub on_hit: Option<OnHit>,
+    #[serde(default)]
+    pub spawn: SpawnRule,
+}
+
+impl AffixTemplate {
+    fn fits(&self, kind: AffixKind, equipment: &Equipment) -> bool {
+        let target = match self.target {
+            AffixTarget::Any => true,
+            AffixTarget::Weapons => equipment.is_weapon(),
+            AffixTarget::Armour => !equipment.is_weapon(),
+        };
+        self.kind == kind && target
+            && (self.slots.is_empty() || self.slots.contains(&equipment.slot))
+    }
+
+    fn apply(&self, equipment: &mut Equipment, strength: i32) {
+        equipment.power_bonus += self.power_bonus * strength;
+        equipment.defense_bonus += self.defense_bonus * strength;
+        equipment.max_hp_bonus += self.max_hp_bonus * strength;
+        for _ in 0..strength {
+            equipment.resistances = equipment.resistances + self.resistances;
+        }
+        if let Some(mut on_hit) = self.on_hit {
+            on_hit.damage *= strength;
+            equipment.on_hit = Some(on_hit);
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("name must not be empty".into());
+        }
+        match self.on_hit {
+            Some(on_hit) if on_hit.damage <= 0 => {
+                return Err("on-hit damage must be positive".into())
+            }
+            _ => {}
+        }
+        self.spawn.validate()
+    }
+}
+
+impl Spawnable for AffixTemplate {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn spawn_rule(&self) -> &SpawnRule {
+        &self.spawn
+    }
+}
+
+/// Turns some equipment into magic items with a random prefix, suffix or both.
+/// The more affixes, the rarer the item.
+pub fn roll_affixes(item: &mut Object, level: u32, data: &GameData) {
+    let mut equipment = match item.equipment {
+        Some(equipment) => equipment,
+        None => return,
+    };
+    let roll = random::rng().next_f32();
+    let (rarity, kinds) = if roll < VERY_RARE_CHANCE {
+        (Rarity::VeryRare, vec![AffixKind::Prefix, AffixKind::Suffix])
+    } else if roll < VERY_RARE_CHANCE + RARE_CHANCE {
+        (Rarity::Rare, vec![AffixKind::Prefix, AffixKind::Suffix])
+    } else if roll < VERY_RARE_CHANCE + RARE_CHANCE + UNCOMMON_CHANCE {
+        let kind = *random::rng()
+            .choose(&[AffixKind::Prefix, AffixKind::Suffix])
+            .unwrap();
+        (Rarity::Uncommon, vec![kind])
+    } else {
+        return;
+    };
+
+    let affixes: Vec<&AffixTemplate> = kinds
+        .into_iter()
+        .filter_map(|kind| {
+            let candidates: Vec<&AffixTemplate> = data.affixes
+                .iter()
+                .filter(|affix| affix.fits(kind, &equipment))
+                .collect();
+            SpawnTable::new(&candidates)
+                .roll(level, &mut random::rng())
+                .cloned()
+        })
+        .collect();
+    if affixes.is_empty() {
+        return;
+    }
+
+    let strength = if rarity == Rarity::VeryRare {
+        VERY_RARE_STRENGTH
+    } else {
+        1
+    };
+    let mut name = item.name.clone();
+    for affix in &affixes {
+        affix.apply(&mut equipment, strength);
+        name = match affix.kind {
+            AffixKind::Prefix => format!("{} {}", affix.name, name),
+            AffixKind::Suffix => format!("{} {}", name, affix.name),
+        };
+    }
+    // Items that only found one of their two affixes count as uncommon.
+    equipment.rarity = if affixes.len() == 1 {
+        Rarity::Uncommon
+    } else {
+        rarity
+    };
+    item.equipment = Some(equipment);
+    item.name = name;
+}
+
+/// The colour that shows how rare a magic item is, if it is magic.
+pub fn rarity_color(item: &Object) -> Option<[f32; 4]> {
+    match item.equipment.map(|equipment| equipment.rarity) {
+        Some(Rarity::Uncommon) => Some(colors::LIGHT_BLUE),
+        Some(Rarity::Rare) => Some(colors::YELLOW),
+        Some(Rarity::VeryRare) => Some(colors::ORANGE),
+        Some(Rarity::Common) | None => None,
+    }
+}
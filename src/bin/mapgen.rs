@@ -0,0 +1,235 @@
+// Generates a single level without opening a window and prints it, for tuning map generation.
+
+extern crate rand;
+extern crate rustlike;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+
+use rustlike::data::GameData;
+use rustlike::map::{self, Generator, Map, TileKind, MAP_HEIGHT, MAP_WIDTH};
+use rustlike::object::Object;
+use rustlike::random;
+use rustlike::PLAYER;
+
+use std::process;
+use std::str::FromStr;
+
+const USAGE: &str = "Usage: mapgen [--seed N] [--level N] [--generator rooms|bsp|caves] \
+                     [--width N] [--height N] [--json]";
+
+struct Options {
+    seed: u64,
+    level: u32,
+    generator: Generator,
+    width: i32,
+    height: i32,
+    json: bool,
+}
+
+/// Everything printed about a generated level.
+#[derive(Serialize)]
+struct Dump {
+    seed: u64,
+    level: u32,
+    generator: Generator,
+    width: i32,
+    height: i32,
+    rows: Vec<String>,
+    objects: Vec<PlacedObject>,
+    stats: Stats,
+}
+
+#[derive(Serialize)]
+struct PlacedObject {
+    name: String,
+    kind: &'static str,
+    glyph: char,
+    x: i32,
+    y: i32,
+}
+
+#[derive(Serialize)]
+struct Stats {
+    rooms: usize,
+    open_tile_ratio: f32,
+    monsters: usize,
+    items: usize,
+}
+
+fn main() {
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{}\n{}", err, USAGE);
+            process::exit(2);
+        }
+    };
+    let data = match GameData::load() {
+        Ok(data) => data,
+        Err(err) => {
+            eprintln!("Failed to load game data: {}", err);
+            process::exit(1);
+        }
+    };
+
+    random::seed(options.seed);
+    let mut objects = vec![Object::new(0, 0, '@', "player", [1.0; 4], true)];
+    let size = (options.width, options.height);
+    let map = match map::make_map_with(&mut objects, options.level, options.generator, size, &data) {
+        Ok(map) => map,
+        Err(err) => {
+            eprintln!("Failed to generate level: {}", err);
+            process::exit(1);
+        }
+    };
+
+    let dump = dump(&options, &map, &objects);
+    if options.json {
+        println!("{}", serde_json::to_string_pretty(&dump).unwrap());
+    } else {
+        print_ascii(&dump);
+    }
+}
+
+fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<Options, String> {
+    let mut seed = None;
+    let mut level = 1;
+    let mut generator = None;
+    let mut width = MAP_WIDTH;
+    let mut height = MAP_HEIGHT;
+    let mut json = false;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--seed" => seed = Some(parse_value(&arg, args.next())?),
+            "--level" => level = parse_value(&arg, args.next())?,
+            "--generator" => {
+                let value: String = parse_value(&arg, args.next())?;
+                generator = Some(match value.as_str() {
+                    "rooms" => Generator::Rooms,
+                    "bsp" => Generator::Bsp,
+                    "caves" => Generator::Caves,
+                    _ => return Err(format!("unknown generator '{}'", value)),
+                });
+            }
+            "--width" => width = parse_value(&arg, args.next())?,
+            "--height" => height = parse_value(&arg, args.next())?,
+            "--json" => json = true,
+            _ => return Err(format!("unknown argument '{}'", arg)),
+        }
+    }
+
+    if level == 0 {
+        return Err("dungeon levels start at 1".into());
+    }
+    if width < 20 || height < 20 {
+        return Err("maps must be at least 20x20".into());
+    }
+    Ok(Options {
+        seed: seed.unwrap_or_else(rand::random),
+        level: level,
+        generator: generator.unwrap_or_else(|| Generator::for_level(level)),
+        width: width,
+        height: height,
+        json: json,
+    })
+}
+
+fn parse_value<T: FromStr>(name: &str, value: Option<String>) -> Result<T, String> {
+    let value = value.ok_or_else(|| format!("{} needs a value", name))?;
+    value
+        .parse()
+        .map_err(|_| format!("invalid value for {}: '{}'", name, value))
+}
+
+fn dump(options: &Options, map: &Map, objects: &[Object]) -> Dump {
+    let mut grid: Vec<Vec<char>> = (0..map.height())
+        .map(|y| (0..map.width()).map(|x| tile_char(map, x, y)).collect())
+        .collect();
+
+    let placed: Vec<PlacedObject> = objects
+        .iter()
+        .enumerate()
+        .map(|(id, object)| PlacedObject {
+            name: object.name.clone(),
+            kind: object_kind(id, object),
+            glyph: object.char,
+            x: object.x,
+            y: object.y,
+        })
+        .collect();
+    // Draw features and items first, so monsters and the player end up on top.
+    let mut drawn: Vec<&PlacedObject> = placed.iter().collect();
+    drawn.sort_by_key(|object| match object.kind {
+        "feature" => 0,
+        "item" => 1,
+        "monster" => 2,
+        _ => 3,
+    });
+    for object in drawn {
+        grid[object.y as usize][object.x as usize] = object.glyph;
+    }
+
+    let open = map.tiles()
+        .iter()
+        .filter(|tile| tile.kind.passable())
+        .count();
+    let stats = Stats {
+        rooms: map.rooms().len(),
+        open_tile_ratio: open as f32 / map.tiles().len() as f32,
+        monsters: placed.iter().filter(|o| o.kind == "monster").count(),
+        items: placed.iter().filter(|o| o.kind == "item").count(),
+    };
+
+    Dump {
+        seed: options.seed,
+        level: options.level,
+        generator: options.generator,
+        width: map.width(),
+        height: map.height(),
+        rows: grid.into_iter().map(|row| row.into_iter().collect()).collect(),
+        objects: placed,
+        stats: stats,
+    }
+}
+
+/// Plain ASCII for a tile. Hidden traps are shown too.
+fn tile_char(map: &Map, x: i32, y: i32) -> char {
+    let tile = &map[(x, y)];
+    if tile.trap.is_some() {
+        return '^';
+    }
+    match tile.kind {
+        TileKind::Floor => '.',
+        TileKind::Wall => '#',
+        TileKind::Tree => 'T',
+        kind => kind.glyph(),
+    }
+}
+
+fn object_kind(id: usize, object: &Object) -> &'static str {
+    if id == PLAYER {
+        "player"
+    } else if object.fighter.is_some() {
+        "monster"
+    } else if object.item.is_some() {
+        "item"
+    } else {
+        "feature"
+    }
+}
+
+fn print_ascii(dump: &Dump) {
+    println!(
+        "Seed {}, dungeon level {}, {:?} generator, {}x{}",
+        dump.seed, dump.level, dump.generator, dump.width, dump.height
+    );
+    for row in &dump.rows {
+        println!("{}", row);
+    }
+    println!("Rooms: {}", dump.stats.rooms);
+    println!("Open tiles: {:.1}%", dump.stats.open_tile_ratio * 100.0);
+    println!("Monsters: {}", dump.stats.monsters);
+    println!("Items: {}", dump.stats.items);
+}
@@ -0,0 +1,58 @@
+use map::Map;
+
+use std::cmp;
+
+/// The part of the map that is shown on screen.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Camera {
+    /// Map position of the top left corner of the view.
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Camera {
+    pub fn new(width: i32, height: i32) -> Self {
+        Camera {
+            x: 0,
+            y: 0,
+            width: width,
+            height: height,
+        }
+    }
+
+    /// Centres the view on a map position, without scrolling past the edges of the map.
+    pub fn center_on(&mut self, x: i32, y: i32, map: &Map) {
+        self.x = clamp_to_map(x - self.width / 2, self.width, map.width());
+        self.y = clamp_to_map(y - self.height / 2, self.height, map.height());
+    }
+
+    /// Translates a map position to a screen cell, or `None` if it is out of view.
+    pub fn to_screen(&self, x: i32, y: i32) -> Option<(i32, i32)> {
+        let (screen_x, screen_y) = (x - self.x, y - self.y);
+        if self.in_view(screen_x, screen_y) {
+            Some((screen_x, screen_y))
+        } else {
+            None
+        }
+    }
+
+    /// Translates a screen cell, such as the one under the mouse, to a map position.
+    pub fn to_map(&self, screen_x: i32, screen_y: i32) -> Option<(i32, i32)> {
+        if self.in_view(screen_x, screen_y) {
+            Some((screen_x + self.x, screen_y + self.y))
+        } else {
+            None
+        }
+    }
+
+    fn in_view(&self, screen_x: i32, screen_y: i32) -> bool {
+        screen_x >= 0 && screen_y >= 0 && screen_x < self.width && screen_y < self.height
+    }
+}
+
+/// Maps smaller than the view stay at the top left corner.
+fn clamp_to_map(start: i32, view_size: i32, map_size: i32) -> i32 {
+    cmp::max(0, cmp::min(start, map_size - view_size))
+}
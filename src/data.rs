@@ -0,0 +1,181 @@
+use item::ItemTemplate;
+use affix::AffixTemplate;
+use monster::MonsterTemplate;
+use spawn::SpawnTable;
+use vault::{VaultFile, VaultSpawn, VaultTemplate};
+
+use serde::de::DeserializeOwned;
+use serde_json;
+
+use std::fmt;
+use std::fs::File;
+use std::io;
+
+const MONSTERS_PATH: &str = "data/monsters.json";
+const ITEMS_PATH: &str = "data/items.json";
+const VAULTS_PATH: &str = "data/vaults.json";
+const AFFIXES_PATH: &str = "data/affixes.json";
+
+/// Chance that a roll on a spawn table uses a deeper level's weights instead.
+const OUT_OF_DEPTH_CHANCE: f32 = 0.05;
+const OUT_OF_DEPTH_LEVELS: u32 = 3;
+
+/// Item the player starts the game with.
+pub const STARTING_WEAPON: &str = "dagger";
+
+/// Everything the game reads from the data directory at startup.
+#[derive(Clone, Debug)]
+pub struct GameData {
+    pub monsters: Vec<MonsterTemplate>,
+    pub items: Vec<ItemTemplate>,
+    pub vaults: Vec<VaultTemplate>,
+    pub affixes: Vec<AffixTemplate>,
+    /// Chance of a random vault on each level.
+    pub vault_chance: f32,
+}
+
+#[derive(Debug)]
+pub enum DataError {
+    Io(String, io::Error),
+    Parse(String, serde_json::Error),
+    Invalid(String, String),
+}
+
+impl fmt::Display for DataError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DataError::Io(ref path, ref err) => write!(f, "{}: could not read file: {}", path, err),
+            DataError::Parse(ref path, ref err) => write!(f, "{}: {}", path, err),
+            DataError::Invalid(ref path, ref msg) => write!(f, "{}: {}", path, msg),
+        }
+    }
+}
+
+impl GameData {
+    /// Loads and validates all data files.
+    pub fn load() -> Result<GameData, DataError> {
+        let monsters: Vec<MonsterTemplate> = read_json(MONSTERS_PATH)?;
+        validate_all(MONSTERS_PATH, &monsters, |m| &m.name, MonsterTemplate::validate)?;
+        let items: Vec<ItemTemplate> = read_json(ITEMS_PATH)?;
+        validate_all(ITEMS_PATH, &items, |i| &i.name, ItemTemplate::validate)?;
+        if !items.iter().any(|item| item.name == STARTING_WEAPON) {
+            return Err(DataError::Invalid(
+                ITEMS_PATH.into(),
+                format!("missing starting item '{}'", STARTING_WEAPON),
+            ));
+        }
+
+        let vault_file: VaultFile = read_json(VAULTS_PATH)?;
+        validate_all(VAULTS_PATH, &vault_file.vaults, |v| &v.name, VaultTemplate::validate)?;
+
+        let affixes: Vec<AffixTemplate> = read_json(AFFIXES_PATH)?;
+        validate_all(AFFIXES_PATH, &affixes, |a| &a.name, AffixTemplate::validate)?;
+
+        let data = GameData {
+            monsters: monsters,
+            items: items,
+            vaults: vault_file.vaults,
+            affixes: affixes,
+            vault_chance: vault_file.chance,
+        };
+        data.validate_vault_legends()?;
+        data.validate_monster_loot()?;
+        Ok(data)
+    }
+
+    /// Checks that every monster and item a vault places is defined.
+    fn validate_vault_legends(&self) -> Result<(), DataError> {
+        for vault in &self.vaults {
+            for spawn in vault.legend.values() {
+                let missing = match *spawn {
+                    VaultSpawn::Monster(ref name) if self.monster(name).is_none() => Some(name),
+                    VaultSpawn::Item(ref name) if self.item(name).is_none() => Some(name),
+                    _ => None,
+                };
+                if let Some(name) = missing {
+                    return Err(DataError::Invalid(
+                        VAULTS_PATH.into(),
+                        format!("entry '{}': unknown spawn '{}'", vault.name, name),
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that every item a monster may carry is defined.
+    fn validate_monster_loot(&self) -> Result<(), DataError> {
+        for monster in &self.monsters {
+            for loot in &monster.loot {
+                if self.item(&loot.item).is_none() {
+                    return Err(DataError::Invalid(
+                        MONSTERS_PATH.into(),
+                        format!("entry '{}': unknown loot '{}'", monster.name, loot.item),
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn monster_table(&self) -> SpawnTable<MonsterTemplate> {
+        SpawnTable::new(&self.monsters).with_out_of_depth(OUT_OF_DEPTH_CHANCE, OUT_OF_DEPTH_LEVELS)
+    }
+
+    pub fn item_table(&self) -> SpawnTable<ItemTemplate> {
+        SpawnTable::new(&self.items).with_out_of_depth(OUT_OF_DEPTH_CHANCE, OUT_OF_DEPTH_LEVELS)
+    }
+
+    pub fn vault_table(&self) -> SpawnTable<VaultTemplate> {
+        SpawnTable::new(&self.vaults)
+    }
+
+    /// Looks up a monster template by name.
+    pub fn monster(&self, name: &str) -> Option<&MonsterTemplate> {
+        self.monsters.iter().find(|monster| monster.name == name)
+    }
+
+    /// Looks up an item template by name.
+    pub fn item(&self, name: &str) -> Option<&ItemTemplate> {
+        self.items.iter().find(|item| item.name == name)
+    }
+}
+
+fn read_json<T: DeserializeOwned>(path: &str) -> Result<T, DataError> {
+    let file = File::open(path).map_err(|e| DataError::Io(path.into(), e))?;
+    serde_json::from_reader(file).map_err(|e| DataError::Parse(path.into(), e))
+}
+
+/// Checks that a list of templates is non-empty, has unique names, and that
+/// each entry passes its own validation.
+fn validate_all<T, N, V>(path: &str, templates: &[T], name: N, validate: V) -> Result<(), DataError>
+where
+    N: Fn(&T) -> &String,
+    V: Fn(&T) -> Result<(), String>,
+{
+    if templates.is_empty() {
+        return Err(DataError::Invalid(path.into(), "no entries defined".into()));
+    }
+    for (index, template) in templates.iter().enumerate() {
+        let template_name = name(template);
+        if templates[..index].iter().any(|other| name(other) == template_name) {
+            return Err(DataError::Invalid(
+                path.into(),
+                format!("duplicate entry '{}'", template_name),
+            ));
+        }
+        validate(template).map_err(|msg| {
+            DataError::Invalid(path.into(), format!("entry '{}': {}", template_name, msg))
+        })?;
+    }
+    Ok(())
+}
+
+/// Checks that a colour is a valid RGBA value.
+pub fn validate_color(color: [f32; 4]) -> Result<(), String> {
+    if color.iter().all(|c| *c >= 0.0 && *c <= 1.0) {
+        Ok(())
+    } else {
+        Err(format!("colour components must be within 0.0 and 1.0, got {:?}", color))
+    }
+}
@@ -0,0 +1,316 @@
+use object::DamageType;
+use trap::TrapKind;
+use ::*;
+
+use std::collections::VecDeque;
+
+/// Something noteworthy that happened in the game world.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum GameEvent {
+    Attacked {
+        attacker: String,
+        target: String,
+        damage: i32,
+        target_is_player: bool,
+    },
+    Missed {
+        attacker: String,
+        target: String,
+    },
+    Damaged {
+        target: String,
+        damage: i32,
+        damage_type: DamageType,
+        target_is_player: bool,
+    },
+    PlayerDied,
+    /// A monster died where it stood, leaving behind whatever it carried.
+    MonsterDied {
+        name: String,
+        x: i32,
+        y: i32,
+    },
+    XpGained {
+        amount: i32,
+    },
+    TrapTriggered {
+        name: String,
+        trap: TrapKind,
+        player: bool,
+    },
+    /// Scroll of detect traps, with the number of traps it revealed.
+    TrapsDetected {
+        count: usize,
+    },
+    /// A hidden trap was spotted, either in passing or by searching for it.
+    TrapFound {
+        trap: TrapKind,
+        searching: bool,
+    },
+    SearchFailed,
+    ItemUsed {
+        name: String,
+    },
+    ItemPickedUp {
+        name: String,
+    },
+    ItemDropped {
+        name: String,
+    },
+    ItemIdentified {
+        appearance: String,
+        name: String,
+    },
+    ItemEnchanted {
+        name: String,
+    },
+    ItemRepaired {
+        name: String,
+    },
+    /// Equipment worn down to less than half of its durability.
+    ItemDamaged {
+        name: String,
+    },
+    ItemBroken {
+        name: String,
+    },
+    /// An item slipped out of the player's pack in deep water.
+    ItemSwept {
+        name: String,
+        washed_ashore: bool,
+    },
+    ConfusionEnded {
+        name: String,
+    },
+    MissileHit {
+        missile: String,
+        target: String,
+        damage: i32,
+    },
+    /// A thrown or fired item came down in lava or deep water.
+    MissileLost {
+        missile: String,
+        lava: bool,
+    },
+}
+
+/// Part of the game that is kept up to date from the events taken off the queue.
+pub trait EventListener {
+    fn on_event(&mut self, event: &GameEvent);
+}
+
+/// Queue of game events, drained once per turn by the game loop.
+#[derive(Default)]
+pub struct EventBus {
+    queue: VecDeque<GameEvent>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn emit(&mut self, event: GameEvent) {
+        self.queue.push_back(event);
+    }
+
+    /// Takes the oldest event off the queue.
+    pub fn next(&mut self) -> Option<GameEvent> {
+        self.queue.pop_front()
+    }
+}
+
+/// Running totals for the current game, kept up to date from the event queue.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Statistics {
+    pub kills: i32,
+    pub damage_dealt: i32,
+    pub damage_taken: i32,
+    pub misses: i32,
+    pub items_used: i32,
+}
+
+impl EventListener for Statistics {
+    fn on_event(&mut self, event: &GameEvent) {
+        match *event {
+            GameEvent::Attacked {
+                damage,
+                target_is_player,
+                ..
+            }
+            | GameEvent::Damaged {
+                damage,
+                target_is_player,
+                ..
+            } => if target_is_player {
+                self.damage_taken += damage;
+            } else {
+                self.damage_dealt += damage;
+            },
+            GameEvent::MissileHit { damage, .. } => self.damage_dealt += damage,
+            GameEvent::Missed { .. } => self.misses += 1,
+            GameEvent::MonsterDied { .. } => self.kills += 1,
+            GameEvent::ItemUsed { .. } => self.items_used += 1,
+            _ => {}
+        }
+    }
+}
+
+impl EventListener for Messages {
+    fn on_event(&mut self, event: &GameEvent) {
+        match *event {
+            GameEvent::Attacked {
+                ref attacker,
+                ref target,
+                damage,
+                ..
+            } => self.add(
+                format!("{} attacks {} for {} hit points.", attacker, target, damage),
+                colors::RED,
+            ),
+            GameEvent::Missed {
+                ref attacker,
+                ref target,
+            } => self.add(
+                format!("{} attacks {} but it has no effect!", attacker, target),
+                colors::WHITE,
+            ),
+            GameEvent::Damaged {
+                ref target,
+                damage,
+                damage_type,
+                ..
+            } => match damage_type {
+                _ if damage <= 0 => self.add(
+                    format!("The {} shrugs off the {}.", target, damage_type),
+                    colors::WHITE,
+                ),
+                DamageType::Lightning => self.add(
+                    format!(
+                        "A lightning bolt strikes the {} with a loud thunder! \
+                         The damage is {} hit points.",
+                        target, damage
+                    ),
+                    colors::LIGHT_BLUE,
+                ),
+                DamageType::Fire => self.add(
+                    format!("The {} gets burned for {} hit points.", target, damage),
+                    colors::ORANGE,
+                ),
+                DamageType::Cold => self.add(
+                    format!(
+                        "A shard of ice pierces the {} for {} hit points.",
+                        target, damage
+                    ),
+                    colors::LIGHT_BLUE,
+                ),
+                _ => self.add(
+                    format!(
+                        "The {} takes {} {} damage.",
+                        target, damage, damage_type
+                    ),
+                    colors::RED,
+                ),
+            },
+            GameEvent::PlayerDied => self.add("You died!", colors::RED),
+            GameEvent::MonsterDied { ref name, .. } => {
+                self.add(format!("{} is dead!", name), colors::ORANGE)
+            }
+            GameEvent::XpGained { amount } => self.add(
+                format!("You gain {} experience points.", amount),
+                colors::ORANGE,
+            ),
+            GameEvent::TrapTriggered {
+                ref name,
+                trap,
+                player,
+            } => if player {
+                self.add(format!("You trigger a {}!", trap), colors::YELLOW)
+            } else {
+                self.add(format!("The {} triggers a {}!", name, trap), colors::YELLOW)
+            },
+            GameEvent::TrapFound { trap, searching } => if searching {
+                self.add(format!("You find a {}!", trap), colors::YELLOW)
+            } else {
+                self.add(format!("You notice a {}!", trap), colors::YELLOW)
+            },
+            GameEvent::TrapsDetected { count } => match count {
+                0 => self.add("You sense no traps nearby.", colors::LIGHT_CYAN),
+                1 => self.add("You sense a trap nearby!", colors::LIGHT_CYAN),
+                n => self.add(format!("You sense {} traps nearby!", n), colors::LIGHT_CYAN),
+            },
+            GameEvent::SearchFailed => {
+                self.add("You search the area but find nothing.", colors::WHITE)
+            }
+            GameEvent::ItemUsed { .. } => {}
+            GameEvent::ItemPickedUp { ref name } => {
+                self.add(format!("You picked up a {}!", name), colors::GREEN)
+            }
+            GameEvent::ItemDropped { ref name } => {
+                self.add(format!("You dropped a {}.", name), colors::YELLOW)
+            }
+            GameEvent::MissileHit {
+                ref missile,
+                ref target,
+                damage,
+            } => if damage > 0 {
+                self.add(
+                    format!("The {} hits the {} for {} hit points.", missile, target, damage),
+                    colors::RED,
+                )
+            } else {
+                self.add(
+                    format!("The {} bounces off the {}.", missile, target),
+                    colors::WHITE,
+                )
+            },
+            GameEvent::MissileLost { ref missile, lava } => if lava {
+                self.add(format!("The {} burns up in the lava.", missile), colors::ORANGE)
+            } else {
+                self.add(
+                    format!("The {} sinks into the deep water.", missile),
+                    colors::LIGHT_BLUE,
+                )
+            },
+            GameEvent::ItemIdentified {
+                ref appearance,
+                ref name,
+            } => self.add(
+                format!("The {} is a {}.", appearance, name),
+                colors::LIGHT_CYAN,
+            ),
+            GameEvent::ItemEnchanted { ref name } => self.add(
+                format!("Your {} glows blue for a moment.", name),
+                colors::LIGHT_BLUE,
+            ),
+            GameEvent::ItemRepaired { ref name } => self.add(
+                format!("You repair your {}. It is as good as new.", name),
+                colors::LIGHT_GREEN,
+            ),
+            GameEvent::ItemDamaged { ref name } => {
+                self.add(format!("Your {} is badly damaged.", name), colors::ORANGE)
+            }
+            GameEvent::ItemBroken { ref name } => {
+                self.add(format!("Your {} breaks!", name), colors::RED)
+            }
+            GameEvent::ItemSwept {
+                ref name,
+                washed_ashore,
+            } => if washed_ashore {
+                self.add(
+                    format!("Your {} slips away and washes up on the shore.", name),
+                    colors::LIGHT_BLUE,
+                )
+            } else {
+                self.add(
+                    format!("Your {} slips away and sinks into the deep water!", name),
+                    colors::LIGHT_BLUE,
+                )
+            },
+            GameEvent::ConfusionEnded { ref name } => self.add(
+                format!("The {} is no longer confused!", name),
+                colors::RED,
+            ),
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use map::{Map, MAP_HEIGHT, MAP_WIDTH};
+use map::Map;
 
 #[derive(Clone, Debug)]
 struct FovTile {
@@ -14,8 +14,8 @@ pub struct Fov {
 }
 
 impl Fov {
-    /// Creates a new blank FOV map using the default size from the Map module.
-    pub fn new() -> Fov {
+    /// Creates a new blank FOV map of the given size.
+    pub fn new(width: i32, height: i32) -> Fov {
         Fov {
             fovmap: vec![
                 vec![
@@ -23,31 +23,38 @@ impl Fov {
                         visible: false,
                         blocks: false,
                     };
-                    MAP_HEIGHT as usize
+                    height as usize
                 ];
-                MAP_WIDTH as usize
+                width as usize
             ],
         }
     }
 
     /// Creates a FOV map, using a Map to determine what tiles block vision.
     pub fn from_map(map: &Map) -> Fov {
-        let mut fovmap = vec![
-            vec![
-                FovTile {
-                    visible: false,
-                    blocks: false,
-                };
-                MAP_HEIGHT as usize
-            ];
-            MAP_WIDTH as usize
-        ];
-        for y in 0..MAP_HEIGHT as usize {
-            for x in 0..MAP_WIDTH as usize {
-                fovmap[x][y].blocks = map[x][y].block_sight;
+        let mut fov = Fov::new(map.width(), map.height());
+        fov.refresh(map);
+        fov
+    }
+
+    /// Updates which tiles block vision, for when tiles such as doors change.
+    pub fn refresh(&mut self, map: &Map) {
+        if (self.width(), self.height()) != (map.width(), map.height()) {
+            *self = Fov::new(map.width(), map.height());
+        }
+        for y in 0..map.height() {
+            for x in 0..map.width() {
+                self.fovmap[x as usize][y as usize].blocks = map[(x, y)].block_sight();
             }
         }
-        Fov { fovmap: fovmap }
+    }
+
+    pub fn width(&self) -> i32 {
+        self.fovmap.len() as i32
+    }
+
+    pub fn height(&self) -> i32 {
+        self.fovmap.first().map_or(0, |column| column.len()) as i32
     }
 
     /// Recomputes FOV from an origin, out to the distance of the radius.
@@ -66,6 +73,9 @@ impl Fov {
         let mut ox = origin_x as f32 + 0.5;
         let mut oy = origin_y as f32 + 0.5;
         for _ in 0..radius {
+            if ox < 0.0 || oy < 0.0 || ox as i32 >= self.width() || oy as i32 >= self.height() {
+                return;
+            }
             self.fovmap[ox as usize][oy as usize].visible = true;
             if self.fovmap[ox as usize][oy as usize].blocks {
                 return;
@@ -84,8 +94,11 @@ impl Fov {
         }
     }
 
-    /// Returns whether or not the coordinate is in FOV.
+    /// Returns whether or not the coordinate is in FOV. Coordinates outside the map never are.
     pub fn is_in_fov(&self, x: i32, y: i32) -> bool {
+        if x < 0 || y < 0 || x >= self.width() || y >= self.height() {
+            return false;
+        }
         self.fovmap[x as usize][y as usize].visible
     }
 }
@@ -0,0 +1,167 @@
+use item::{ItemCategory, ItemTemplate};
+use affix::rarity_color;
+use object::Object;
+use colors;
+
+use rand::Rng;
+use random;
+use std::collections::{HashMap, HashSet};
+use std::vec;
+
+/// Looks potions can have before they are identified. Each game shuffles them.
+const POTION_APPEARANCES: [(&str, [f32; 4]); 10] = [
+    ("bubbling red potion", colors::RED),
+    ("murky green potion", colors::GREEN),
+    ("fizzy yellow potion", colors::YELLOW),
+    ("cloudy white potion", colors::WHITE),
+    ("glowing violet potion", colors::VIOLET),
+    ("smoky grey potion", colors::LIGHT_GREY),
+    ("oily orange potion", colors::ORANGE),
+    ("sparkling blue potion", colors::LIGHT_BLUE),
+    ("dark red potion", colors::DARK_RED),
+    ("pale cyan potion", colors::LIGHT_CYAN),
+];
+
+/// Looks wands can have before they are identified.
+const WAND_APPEARANCES: [(&str, [f32; 4]); 8] = [
+    ("oak wand", colors::ORANGE),
+    ("bone wand", colors::WHITE),
+    ("iron wand", colors::LIGHT_GREY),
+    ("crystal wand", colors::LIGHT_CYAN),
+    ("ebony wand", colors::DARK_RED),
+    ("copper wand", colors::LIGHT_RED),
+    ("jade wand", colors::LIGHT_GREEN),
+    ("silver wand", colors::LIGHT_BLUE),
+];
+
+/// Syllables that make up the labels of unidentified scrolls.
+const SCROLL_SYLLABLES: [&str; 16] = [
+    "ab", "ra", "ka", "zel", "go", "mer", "xu", "pho", "ne", "lor", "vun", "eth", "ix", "ba",
+    "tor", "qua",
+];
+const SCROLL_LABEL_WORDS: usize = 2;
+
+/// What an unidentified item looks like.
+#[derive(Clone, Debug)]
+pub struct Appearance {
+    pub name: String,
+    pub color: [f32; 4],
+}
+
+/// Which potions, scrolls and wands the player has identified, and how the others appear.
+/// Items are tracked by their true name.
+#[derive(Clone, Debug, Default)]
+pub struct Identification {
+    appearances: HashMap<String, Appearance>,
+    known: HashSet<String>,
+}
+
+impl Identification {
+    /// Gives every potion, scroll and wand a random appearance for a new game.
+    pub fn new(items: &[ItemTemplate]) -> Self {
+        let mut potions = shuffled(&POTION_APPEARANCES);
+        let mut wands = shuffled(&WAND_APPEARANCES);
+        let mut labels = HashSet::new();
+
+        let mut appearances = HashMap::new();
+        for template in items {
+            // Items past the number of appearances are known from the start.
+            let appearance = match template.category() {
+                ItemCategory::Potions => match potions.next() {
+                    Some(appearance) => appearance,
+                    None => continue,
+                },
+                ItemCategory::Wands => match wands.next() {
+                    Some(appearance) => appearance,
+                    None => continue,
+                },
+                ItemCategory::Scrolls => Appearance {
+                    name: format!("scroll labelled {}", unique_label(&mut labels)),
+                    color: template.color,
+                },
+                _ => continue,
+            };
+            appearances.insert(template.name.clone(), appearance);
+        }
+
+        Identification {
+            appearances: appearances,
+            known: HashSet::new(),
+        }
+    }
+
+    /// The appearance of an item the player hasn't identified yet.
+    pub fn appearance(&self, item: &Object) -> Option<&Appearance> {
+        if item.item.is_none() || self.known.contains(&item.name) {
+            None
+        } else {
+            self.appearances.get(&item.name)
+        }
+    }
+
+    /// Whether the player knows all about an item, including the enchantment of equipment.
+    pub fn is_known(&self, item: &Object) -> bool {
+        self.appearance(item).is_none() && item.equipment.map_or(true, |e| e.identified)
+    }
+
+    /// The name the player knows an object by.
+    pub fn name_of(&self, object: &Object) -> String {
+        if let Some(appearance) = self.appearance(object) {
+            return appearance.name.clone();
+        }
+        match object.equipment {
+            Some(equipment) if equipment.identified => {
+                let curse = if equipment.cursed { "cursed " } else { "" };
+                format!("{}{:+} {}", curse, equipment.enchantment, object.name)
+            }
+            _ => object.name.clone(),
+        }
+    }
+
+    /// The colour an object is drawn in, which also shows how rare magic equipment is.
+    pub fn color_of(&self, object: &Object) -> [f32; 4] {
+        match self.appearance(object) {
+            Some(appearance) => appearance.color,
+            None => rarity_color(object).unwrap_or(object.color),
+        }
+    }
+
+    /// Marks every item with this one's name as identified. Returns false if it already was.
+    /// Equipment is identified separately for each item.
+    pub fn identify(&mut self, item: &Object) -> bool {
+        if self.appearance(item).is_none() {
+            return false;
+        }
+        self.known.insert(item.name.clone())
+    }
+}
+
+fn shuffled(appearances: &[(&str, [f32; 4])]) -> vec::IntoIter<Appearance> {
+    let mut appearances: Vec<Appearance> = appearances
+        .iter()
+        .map(|&(name, color)| Appearance {
+            name: name.into(),
+            color: color,
+        })
+        .collect();
+    random::rng().shuffle(&mut appearances);
+    appearances.into_iter()
+}
+
+fn unique_label(used: &mut HashSet<String>) -> String {
+    loop {
+        let label = (0..SCROLL_LABEL_WORDS)
+            .map(|_| {
+                let syllables = random::rng().gen_range(1, 4);
+                (0..syllables)
+                    .map(|_| *random::rng().choose(&SCROLL_SYLLABLES).unwrap())
+                    .collect::<String>()
+                    .to_uppercase()
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        if used.insert(label.clone()) {
+            return label;
+        }
+    }
+}
@@ -1,7 +1,15 @@
 use object::*;
+use data::validate_color;
+use spawn::{Rarity, SpawnRule, Spawnable};
+use affix::OnHit;
 use renderer::render_all;
+use event::GameEvent;
+use trap::reveal_traps;
 use ::*;
 
+use rand::Rng;
+use random;
+
 const HEAL_AMOUNT: i32 = 40;
 const LIGHTNING_DAMAGE: i32 = 40;
 const LIGHTNING_RANGE: i32 = 5;
@@ -9,33 +17,183 @@ const CONFUSE_RANGE: i32 = 8;
 const CONFUSE_NUM_TURNS: i32 = 10;
 const FIREBALL_RADIUS: i32 = 3;
 const FIREBALL_DAMAGE: i32 = 25;
+const FROST_RANGE: i32 = 8;
+const FROST_DAMAGE: i32 = 30;
+const DETECT_TRAPS_RADIUS: i32 = 20;
+const CURSED_CHANCE: f32 = 0.1;
+const ENCHANTED_CHANCE: f32 = 0.2;
+/// Largest enchantment, either way, that equipment is found with.
+const MAX_ENCHANTMENT: i32 = 3;
+/// Number of separate entries, not items, the inventory can hold.
+pub const INVENTORY_SIZE: usize = 26;
 
 #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Slot {
     LeftHand,
     RightHand,
     Head,
+    Body,
+    Hands,
+    Feet,
+    Neck,
+    LeftRing,
+    RightRing,
+    /// Bows and slings, which fire ammunition from the inventory.
+    Ranged,
 }
 
+/// Every slot, in the order the equipment screen lists them.
+pub const ALL_SLOTS: [Slot; 10] = [
+    Slot::RightHand,
+    Slot::LeftHand,
+    Slot::Ranged,
+    Slot::Head,
+    Slot::Neck,
+    Slot::Body,
+    Slot::Hands,
+    Slot::LeftRing,
+    Slot::RightRing,
+    Slot::Feet,
+];
+
 #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Equipment {
     pub slot: Slot,
+    #[serde(default)]
     pub equipped: bool,
+    /// Two-handed weapons use the right hand slot and block the left hand as well.
+    #[serde(default)]
+    pub two_handed: bool,
+    /// Weapons are held for their power rather than their defense. Launchers always are.
+    #[serde(default)]
+    pub weapon: bool,
+    #[serde(default)]
     pub power_bonus: i32,
+    #[serde(default)]
     pub defense_bonus: i32,
+    #[serde(default)]
     pub max_hp_bonus: i32,
+    #[serde(default)]
+    pub resistances: Resistances,
+    /// Only for equipment in the ranged slot.
+    #[serde(default)]
+    pub launcher: Option<Launcher>,
+    /// Added to the power of weapons, the damage of launchers and the defense of anything else.
+    #[serde(default)]
+    pub enchantment: i32,
+    /// Cursed equipment can't be taken off once it is worn.
+    #[serde(default)]
+    pub cursed: bool,
+    /// Whether the player knows the enchantment and curse.
+    #[serde(default)]
+    pub identified: bool,
+    #[serde(default)]
+    pub on_hit: Option<OnHit>,
+    /// Magic items with affixes are uncommon or rarer.
+    #[serde(default)]
+    pub rarity: Rarity,
+    /// Uses before the item breaks. Items with no maximum never wear down.
+    #[serde(default)]
+    pub durability: i32,
+    #[serde(default)]
+    pub max_durability: i32,
+}
+
+/// Ammunition a launcher fires.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum AmmoKind {
+    Arrow,
+    Stone,
 }
 
+/// A ranged weapon such as a bow.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Launcher {
+    pub ammo: AmmoKind,
+    /// Added to the damage of the ammunition.
+    pub damage: i32,
+    pub range: i32,
+}
+
+/// How an item does when thrown or fired. Items without this do little damage when thrown.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Missile {
+    pub damage: i32,
+    /// The kind of launcher that can fire this item, if any.
+    #[serde(default)]
+    pub ammo: Option<AmmoKind>,
+}
+
+/// What happens when an item is used.
 #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Item {
     Heal,
     Lightning,
     Confuse,
     Fireball,
-    Sword,
-    Shield,
+    Frost,
+    DetectTraps,
+    Identify,
+    Recharge,
+    Enchant,
+    RemoveCurse,
+    Repair,
+    Equip,
+    Ammo,
+}
+
+/// Uses left in an item such as a wand.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Charges {
+    pub current: u32,
+    pub max: u32,
+}
+
+/// A kind of item as defined in the data files.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ItemTemplate {
+    pub name: String,
+    pub glyph: char,
+    pub color: [f32; 4],
+    pub effect: Item,
+    #[serde(default)]
+    pub equipment: Option<Equipment>,
+    /// Stackable items of the same kind share one inventory entry.
+    #[serde(default)]
+    pub stackable: bool,
+    /// Most charges the item can hold. Items start with between half and all of them.
+    #[serde(default)]
+    pub charges: Option<u32>,
+    #[serde(default)]
+    pub missile: Option<Missile>,
+    /// Items without a spawn rule are never placed randomly.
+    #[serde(default)]
+    pub spawn: SpawnRule,
+}
+
+/// Groups items are listed under in the inventory.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ItemCategory {
+    Weapons,
+    Armour,
+    Jewellery,
+    Wands,
+    Potions,
+    Scrolls,
+    Tools,
 }
 
+/// Every category, in the order the inventory lists them.
+pub const ALL_CATEGORIES: [ItemCategory; 7] = [
+    ItemCategory::Weapons,
+    ItemCategory::Armour,
+    ItemCategory::Jewellery,
+    ItemCategory::Wands,
+    ItemCategory::Potions,
+    ItemCategory::Scrolls,
+    ItemCategory::Tools,
+];
+
 #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 enum UseResult {
     UsedUp,
@@ -49,44 +207,289 @@ impl std::fmt::Display for Slot {
             Slot::LeftHand => write!(f, "left hand"),
             Slot::RightHand => write!(f, "right hand"),
             Slot::Head => write!(f, "head"),
+            Slot::Body => write!(f, "body"),
+            Slot::Hands => write!(f, "hands"),
+            Slot::Feet => write!(f, "feet"),
+            Slot::Neck => write!(f, "neck"),
+            Slot::LeftRing => write!(f, "left ring finger"),
+            Slot::RightRing => write!(f, "right ring finger"),
+            Slot::Ranged => write!(f, "ranged"),
         }
     }
 }
 
+impl std::fmt::Display for ItemCategory {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            ItemCategory::Weapons => write!(f, "Weapons"),
+            ItemCategory::Armour => write!(f, "Armour"),
+            ItemCategory::Jewellery => write!(f, "Jewellery"),
+            ItemCategory::Wands => write!(f, "Wands"),
+            ItemCategory::Potions => write!(f, "Potions"),
+            ItemCategory::Scrolls => write!(f, "Scrolls"),
+            ItemCategory::Tools => write!(f, "Tools"),
+        }
+    }
+}
+
+impl Slot {
+    /// Another slot an item for this slot fits in, like the other hand for rings.
+    pub fn alternative(self) -> Option<Slot> {
+        match self {
+            Slot::LeftRing => Some(Slot::RightRing),
+            Slot::RightRing => Some(Slot::LeftRing),
+            _ => None,
+        }
+    }
+}
+
+impl Equipment {
+    /// The slots this equipment takes up when worn.
+    pub fn slots(&self) -> Vec<Slot> {
+        if self.two_handed {
+            vec![Slot::RightHand, Slot::LeftHand]
+        } else {
+            vec![self.slot]
+        }
+    }
+
+    /// Power bonus, including enchantment and wear.
+    pub fn power(&self) -> i32 {
+        if self.launcher.is_none() && self.is_weapon() {
+            self.worn_down(self.power_bonus + self.enchantment)
+        } else {
+            self.worn_down(self.power_bonus)
+        }
+    }
+
+    /// Defense bonus, including enchantment and wear.
+    pub fn defense(&self) -> i32 {
+        if self.launcher.is_none() && !self.is_weapon() {
+            self.worn_down(self.defense_bonus + self.enchantment)
+        } else {
+            self.worn_down(self.defense_bonus)
+        }
+    }
+
+    /// Everything that isn't a weapon counts as armour.
+    pub fn is_weapon(&self) -> bool {
+        self.weapon || self.launcher.is_some()
+    }
+
+    /// Damaged equipment has less than half of its durability left.
+    pub fn is_damaged(&self) -> bool {
+        self.max_durability > 0 && self.durability * 2 < self.max_durability
+    }
+
+    /// Damaged equipment only gives half of its bonuses.
+    fn worn_down(&self, bonus: i32) -> i32 {
+        if self.is_damaged() && bonus > 0 {
+            bonus / 2
+        } else {
+            bonus
+        }
+    }
+
+    /// Where the equipment is worn, as shown to the player.
+    pub fn worn_on(&self) -> String {
+        if self.two_handed {
+            "both hands".into()
+        } else {
+            self.slot.to_string()
+        }
+    }
+}
+
+/// The inventory group an item belongs in.
+pub fn item_category(item: &Object) -> ItemCategory {
+    category(item.item, item.equipment, item.charges.is_some())
+}
+
+fn category(effect: Option<Item>, equipment: Option<Equipment>, charged: bool) -> ItemCategory {
+    match (effect, equipment) {
+        _ if charged => ItemCategory::Wands,
+        (Some(Item::Heal), _) => ItemCategory::Potions,
+        (Some(Item::Ammo), _) => ItemCategory::Weapons,
+        (Some(Item::Repair), _) => ItemCategory::Tools,
+        (Some(Item::Equip), Some(equipment)) => match equipment.slot {
+            _ if equipment.is_weapon() => ItemCategory::Weapons,
+            Slot::Neck | Slot::LeftRing | Slot::RightRing => ItemCategory::Jewellery,
+            _ => ItemCategory::Armour,
+        },
+        _ => ItemCategory::Scrolls,
+    }
+}
+
+impl ItemTemplate {
+    pub fn category(&self) -> ItemCategory {
+        category(Some(self.effect), self.equipment, self.charges.is_some())
+    }
+
+    /// Creates an item from this template at the given position.
+    pub fn spawn(&self, x: i32, y: i32) -> Object {
+        let mut item = Object::new(x, y, self.glyph, &self.name, self.color, false);
+        item.item = Some(self.effect);
+        item.equipment = self.equipment;
+        if let Some(ref mut equipment) = item.equipment {
+            equipment.durability = equipment.max_durability;
+            roll_enchantment(equipment);
+        }
+        item.missile = self.missile;
+        if self.stackable {
+            item.quantity = Some(1);
+        }
+        if let Some(max) = self.charges {
+            item.charges = Some(Charges {
+                current: random::rng().gen_range((max + 1) / 2, max + 1),
+                max: max,
+            });
+        }
+        item
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("name must not be empty".into());
+        }
+        if self.glyph.is_whitespace() {
+            return Err("glyph must be a visible character".into());
+        }
+        validate_color(self.color)?;
+        match (self.effect, self.equipment) {
+            (Item::Equip, None) => return Err("effect Equip requires equipment".into()),
+            (Item::Equip, Some(equipment)) if equipment.equipped => {
+                return Err("equipment must not start out equipped".into())
+            }
+            (Item::Equip, Some(equipment)) if equipment.two_handed
+                && equipment.slot != Slot::RightHand =>
+            {
+                return Err("two-handed equipment must use the RightHand slot".into())
+            }
+            (Item::Equip, Some(equipment))
+                if (equipment.slot == Slot::Ranged) != equipment.launcher.is_some() =>
+            {
+                return Err("launchers must use the Ranged slot, and only launchers".into())
+            }
+            (Item::Equip, Some(equipment)) if equipment.weapon
+                && equipment.slot != Slot::LeftHand
+                && equipment.slot != Slot::RightHand =>
+            {
+                return Err("weapons must use the LeftHand or RightHand slot".into())
+            }
+            (Item::Equip, Some(equipment)) if equipment.max_durability < 0 => {
+                return Err("max_durability must not be negative".into())
+            }
+            (Item::Equip, Some(_)) | (_, None) => {}
+            (effect, Some(_)) => {
+                return Err(format!("effect {:?} cannot have equipment", effect))
+            }
+        }
+        if self.stackable && self.equipment.is_some() {
+            return Err("equipment cannot be stackable".into());
+        }
+        match self.charges {
+            Some(0) => return Err("charges must be at least 1".into()),
+            Some(_) if self.stackable => {
+                return Err("items with charges cannot be stackable".into())
+            }
+            Some(_) if self.equipment.is_some() => {
+                return Err("equipment cannot have charges".into())
+            }
+            _ => {}
+        }
+        match self.missile {
+            Some(missile) if missile.damage < 0 => {
+                return Err("missile damage must not be negative".into())
+            }
+            None if self.effect == Item::Ammo => return Err("effect Ammo requires missile".into()),
+            _ => {}
+        }
+        self.spawn.validate()
+    }
+}
+
+/// Makes some equipment better or worse than usual, and curses some of the worse.
+fn roll_enchantment(equipment: &mut Equipment) {
+    let roll = random::rng().next_f32();
+    let amount = random::rng().gen_range(1, MAX_ENCHANTMENT + 1);
+    if roll < CURSED_CHANCE {
+        equipment.enchantment -= amount;
+        equipment.cursed = true;
+    } else if roll < CURSED_CHANCE + ENCHANTED_CHANCE {
+        equipment.enchantment += amount;
+    }
+}
+
+impl Spawnable for ItemTemplate {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn spawn_rule(&self) -> &SpawnRule {
+        &self.spawn
+    }
+}
+
 pub fn pick_item_up(object_id: usize, objects: &mut Vec<Object>, game: &mut Game) {
-    if game.inventory.len() >= 26 {
+    let stack = game.inventory
+        .iter()
+        .position(|item| objects[object_id].stacks_with(item));
+    if let Some(stack) = stack {
+        let item = objects.swap_remove(object_id);
+        game.events.emit(GameEvent::ItemPickedUp {
+            name: game.identification.name_of(&item),
+        });
+        let quantity = game.inventory[stack].quantity.unwrap_or(1);
+        game.inventory[stack].quantity = Some(quantity + item.quantity.unwrap_or(1));
+    } else if game.inventory.len() >= INVENTORY_SIZE {
         game.log.add(
             format!(
                 "Your inventory is full, cannot pick up {}.",
-                objects[object_id].name
+                game.identification.name_of(&objects[object_id])
             ),
             colors::RED,
         );
     } else {
         let item = objects.swap_remove(object_id);
-        game.log
-            .add(format!("You picked up a {}!", item.name), colors::GREEN);
+        game.events.emit(GameEvent::ItemPickedUp {
+            name: game.identification.name_of(&item),
+        });
         let index = game.inventory.len();
-        let slot = item.equipment.map(|e| e.slot);
         game.inventory.push(item);
 
-        // Autoequip
-        if let Some(slot) = slot {
-            if get_equipment_in_slot(slot, &game.inventory).is_none() {
-                game.inventory[index].equip(&mut game.log);
-            }
+        // Autoequip, but never put on something that might turn out to be cursed.
+        let identified = game.inventory[index].equipment.map_or(false, |e| e.identified);
+        if identified && free_slot(index, &game.inventory).is_some() {
+            equip_item(index, &mut game.inventory, &game.identification, &mut game.log);
         }
     }
 }
 
-pub fn drop_item(inventory_id: usize, objects: &mut Vec<Object>, game: &mut Game) {
-    let mut item = game.inventory.remove(inventory_id);
+/// Takes a number of items off an inventory entry, removing the entry once its stack is gone.
+pub fn take_from_inventory(inventory: &mut Vec<Object>, inventory_id: usize, count: u32) -> Object {
+    match inventory[inventory_id].quantity {
+        Some(quantity) if count < quantity => {
+            inventory[inventory_id].quantity = Some(quantity - count);
+            let mut taken = inventory[inventory_id].clone();
+            taken.quantity = Some(count);
+            taken
+        }
+        _ => inventory.remove(inventory_id),
+    }
+}
+
+pub fn drop_item(inventory_id: usize, count: u32, objects: &mut Vec<Object>, game: &mut Game) {
+    if is_stuck(&game.inventory[inventory_id], &game.identification, &mut game.log) {
+        return;
+    }
+    let mut item = take_from_inventory(&mut game.inventory, inventory_id, count);
     if item.equipment.is_some() {
         item.unequip(&mut game.log);
     }
     item.set_pos(objects[PLAYER].x, objects[PLAYER].y);
-    game.log
-        .add(format!("You dropped a {}.", item.name), colors::YELLOW);
+    game.events.emit(GameEvent::ItemDropped {
+        name: game.identification.name_of(&item),
+    });
     objects.push(item);
 }
 
@@ -98,14 +501,39 @@ pub fn use_item(inventory_id: usize, objects: &mut [Object], ui: &mut Ui, game:
             Lightning => cast_lightning,
             Confuse => cast_confuse,
             Fireball => cast_fireball,
-            Sword => toggle_equipment,
-            Shield => toggle_equipment,
+            Frost => cast_frost,
+            DetectTraps => cast_detect_traps,
+            Identify => cast_identify,
+            Recharge => cast_recharge,
+            Enchant => cast_enchant,
+            RemoveCurse => cast_remove_curse,
+            Repair => cast_repair,
+            Equip => toggle_equipment,
+            Ammo => cast_ammo,
         };
-        match on_use(inventory_id, objects, game, ui) {
+        if let Some(Charges { current: 0, .. }) = game.inventory[inventory_id].charges {
+            game.log.add("Nothing happens. It must be out of charges.", colors::WHITE);
+            return;
+        }
+        let name = game.inventory[inventory_id].name.clone();
+        let mut result = on_use(inventory_id, objects, game, ui);
+        if result == UseResult::UsedUp {
+            // Charged items spend a charge instead of being used up.
+            if let Some(ref mut charges) = game.inventory[inventory_id].charges {
+                charges.current -= 1;
+                result = UseResult::UsedAndKept;
+            }
+        }
+        if result != UseResult::Cancelled {
+            // Using an item shows what it was.
+            identify_item(inventory_id, game);
+        }
+        match result {
             UseResult::UsedUp => {
-                game.inventory.remove(inventory_id);
+                take_from_inventory(&mut game.inventory, inventory_id, 1);
+                game.events.emit(GameEvent::ItemUsed { name: name });
             }
-            UseResult::UsedAndKept => {}
+            UseResult::UsedAndKept => game.events.emit(GameEvent::ItemUsed { name: name }),
             UseResult::Cancelled => game.log.add("Cancelled", colors::WHITE),
         }
     } else {
@@ -116,6 +544,23 @@ pub fn use_item(inventory_id: usize, objects: &mut [Object], ui: &mut Ui, game:
     }
 }
 
+/// Identifies every item of the same kind as an inventory entry.
+/// Equipment is identified one item at a time, showing its enchantment and curse.
+fn identify_item(inventory_id: usize, game: &mut Game) {
+    let appearance = game.identification.name_of(&game.inventory[inventory_id]);
+    let mut identified = game.identification.identify(&game.inventory[inventory_id]);
+    if let Some(ref mut equipment) = game.inventory[inventory_id].equipment {
+        identified |= !equipment.identified;
+        equipment.identified = true;
+    }
+    if identified {
+        game.events.emit(GameEvent::ItemIdentified {
+            appearance: appearance,
+            name: game.identification.name_of(&game.inventory[inventory_id]),
+        });
+    }
+}
+
 fn cast_heal(
     _inventory_id: usize,
     objects: &mut [Object],
@@ -136,6 +581,187 @@ fn cast_heal(
     UseResult::Cancelled
 }
 
+fn cast_identify(
+    inventory_id: usize,
+    _objects: &mut [Object],
+    game: &mut Game,
+    ui: &mut Ui,
+) -> UseResult {
+    let anything_unknown = game.inventory
+        .iter()
+        .enumerate()
+        .any(|(id, item)| id != inventory_id && !game.identification.is_known(item));
+    if !anything_unknown {
+        game.log.add("You have nothing to identify.", colors::RED);
+        return UseResult::Cancelled;
+    }
+
+    let chosen = inventory_menu(
+        &game.inventory,
+        &game.identification,
+        "Press the key next to an item to identify it, or any other to cancel.\n",
+        &mut ui.root,
+    );
+    match chosen {
+        Some(id) if !game.identification.is_known(&game.inventory[id]) => {
+            identify_item(id, game);
+            UseResult::UsedUp
+        }
+        Some(id) => {
+            game.log.add(
+                format!("You already know the {}.", game.inventory[id].name),
+                colors::WHITE,
+            );
+            UseResult::Cancelled
+        }
+        None => UseResult::Cancelled,
+    }
+}
+
+fn cast_recharge(
+    inventory_id: usize,
+    _objects: &mut [Object],
+    game: &mut Game,
+    ui: &mut Ui,
+) -> UseResult {
+    if !game.inventory.iter().any(|item| item.charges.is_some()) {
+        game.log.add("You have nothing to recharge.", colors::RED);
+        return UseResult::Cancelled;
+    }
+
+    let chosen = inventory_menu(
+        &game.inventory,
+        &game.identification,
+        "Press the key next to an item to recharge it, or any other to cancel.\n",
+        &mut ui.root,
+    );
+    let chosen = match chosen {
+        Some(id) if id != inventory_id => id,
+        _ => return UseResult::Cancelled,
+    };
+    let name = game.identification.name_of(&game.inventory[chosen]);
+    match game.inventory[chosen].charges {
+        Some(ref mut charges) => {
+            charges.current = charges.max;
+            game.log.add(
+                format!("Your {} hums with renewed power.", name),
+                colors::LIGHT_VIOLET,
+            );
+            UseResult::UsedUp
+        }
+        None => {
+            game.log.add(format!("The {} cannot be recharged.", name), colors::WHITE);
+            UseResult::Cancelled
+        }
+    }
+}
+
+fn cast_enchant(
+    inventory_id: usize,
+    _objects: &mut [Object],
+    game: &mut Game,
+    ui: &mut Ui,
+) -> UseResult {
+    if !game.inventory.iter().any(|item| item.equipment.is_some()) {
+        game.log.add("You have nothing to enchant.", colors::RED);
+        return UseResult::Cancelled;
+    }
+
+    let chosen = inventory_menu(
+        &game.inventory,
+        &game.identification,
+        "Press the key next to an item to enchant it, or any other to cancel.\n",
+        &mut ui.root,
+    );
+    let chosen = match chosen {
+        Some(id) if id != inventory_id => id,
+        _ => return UseResult::Cancelled,
+    };
+    let name = game.identification.name_of(&game.inventory[chosen]);
+    match game.inventory[chosen].equipment {
+        Some(ref mut equipment) => {
+            equipment.enchantment += 1;
+            game.events.emit(GameEvent::ItemEnchanted { name: name });
+            UseResult::UsedUp
+        }
+        None => {
+            game.log.add(format!("The {} cannot be enchanted.", name), colors::WHITE);
+            UseResult::Cancelled
+        }
+    }
+}
+
+fn cast_remove_curse(
+    _inventory_id: usize,
+    _objects: &mut [Object],
+    game: &mut Game,
+    _ui: &mut Ui,
+) -> UseResult {
+    for item in &mut game.inventory {
+        if let Some(ref mut equipment) = item.equipment {
+            equipment.cursed = false;
+        }
+    }
+    game.log.add(
+        "You feel as if someone is watching over you.",
+        colors::LIGHT_VIOLET,
+    );
+    UseResult::UsedUp
+}
+
+fn cast_repair(
+    inventory_id: usize,
+    _objects: &mut [Object],
+    game: &mut Game,
+    ui: &mut Ui,
+) -> UseResult {
+    let worn = |item: &Object| {
+        item.equipment
+            .map_or(false, |e| e.durability < e.max_durability)
+    };
+    if !game.inventory.iter().any(&worn) {
+        game.log.add("You have nothing to repair.", colors::RED);
+        return UseResult::Cancelled;
+    }
+
+    let chosen = inventory_menu(
+        &game.inventory,
+        &game.identification,
+        "Press the key next to an item to repair it, or any other to cancel.\n",
+        &mut ui.root,
+    );
+    let chosen = match chosen {
+        Some(id) if id != inventory_id => id,
+        _ => return UseResult::Cancelled,
+    };
+    let name = game.identification.name_of(&game.inventory[chosen]);
+    if !worn(&game.inventory[chosen]) {
+        game.log.add(format!("The {} needs no repair.", name), colors::WHITE);
+        return UseResult::Cancelled;
+    }
+    if let Some(ref mut equipment) = game.inventory[chosen].equipment {
+        equipment.durability = equipment.max_durability;
+    }
+    game.events.emit(GameEvent::ItemRepaired { name: name });
+    UseResult::UsedUp
+}
+
+fn cast_ammo(
+    inventory_id: usize,
+    _objects: &mut [Object],
+    game: &mut Game,
+    _ui: &mut Ui,
+) -> UseResult {
+    game.log.add(
+        format!(
+            "The {} can only be fired or thrown.",
+            game.inventory[inventory_id].name
+        ),
+        colors::WHITE,
+    );
+    UseResult::Cancelled
+}
+
 fn cast_lightning(
     _inventory_id: usize,
     objects: &mut [Object],
@@ -144,16 +770,16 @@ fn cast_lightning(
 ) -> UseResult {
     let monster_id = closest_monster(LIGHTNING_RANGE, objects, ui);
     if let Some(monster_id) = monster_id {
-        game.log.add(
-            format!(
-                "A lightning bolt strikes the {} with a loud thunder! \
-                 The damage is {} hit points.",
-                objects[monster_id].name, LIGHTNING_DAMAGE
-            ),
-            colors::LIGHT_BLUE,
-        );
-        if let Some(xp) = objects[monster_id].take_damage(LIGHTNING_DAMAGE, &mut game.log) {
-            objects[PLAYER].fighter.as_mut().unwrap().xp += xp;
+        let damage =
+            objects[monster_id].resisted_damage(LIGHTNING_DAMAGE, DamageType::Lightning, game);
+        game.events.emit(GameEvent::Damaged {
+            target: objects[monster_id].name.clone(),
+            damage: damage,
+            damage_type: DamageType::Lightning,
+            target_is_player: false,
+        });
+        if let Some(xp) = objects[monster_id].take_damage(damage, &mut game.events) {
+            game.events.emit(GameEvent::XpGained { amount: xp });
         };
         UseResult::UsedUp
     } else {
@@ -220,14 +846,14 @@ fn cast_fireball(
     let mut xp_to_gain = 0;
     for (id, obj) in objects.iter_mut().enumerate() {
         if obj.distance(x, y) <= FIREBALL_RADIUS as f32 && obj.fighter.is_some() {
-            game.log.add(
-                format!(
-                    "The {} gets burned for {} hit points.",
-                    obj.name, FIREBALL_DAMAGE
-                ),
-                colors::ORANGE,
-            );
-            if let Some(xp) = obj.take_damage(FIREBALL_DAMAGE, &mut game.log) {
+            let damage = obj.resisted_damage(FIREBALL_DAMAGE, DamageType::Fire, game);
+            game.events.emit(GameEvent::Damaged {
+                target: obj.name.clone(),
+                damage: damage,
+                damage_type: DamageType::Fire,
+                target_is_player: id == PLAYER,
+            });
+            if let Some(xp) = obj.take_damage(damage, &mut game.events) {
                 if id != PLAYER {
                     xp_to_gain += xp;
                 }
@@ -235,11 +861,54 @@ fn cast_fireball(
         }
     }
 
-    objects[PLAYER].fighter.as_mut().unwrap().xp += xp_to_gain;
+    if xp_to_gain > 0 {
+        game.events.emit(GameEvent::XpGained { amount: xp_to_gain });
+    }
 
     UseResult::UsedUp
 }
 
+fn cast_frost(
+    _inventory_id: usize,
+    objects: &mut [Object],
+    game: &mut Game,
+    ui: &mut Ui,
+) -> UseResult {
+    game.log.add(
+        "Left-click an enemy to freeze it, or right-click to cancel.",
+        colors::LIGHT_CYAN,
+    );
+    let monster_id = target_monster(ui, objects, game, Some(FROST_RANGE as f32));
+    if let Some(monster_id) = monster_id {
+        let damage = objects[monster_id].resisted_damage(FROST_DAMAGE, DamageType::Cold, game);
+        game.events.emit(GameEvent::Damaged {
+            target: objects[monster_id].name.clone(),
+            damage: damage,
+            damage_type: DamageType::Cold,
+            target_is_player: false,
+        });
+        if let Some(xp) = objects[monster_id].take_damage(damage, &mut game.events) {
+            game.events.emit(GameEvent::XpGained { amount: xp });
+        };
+        UseResult::UsedUp
+    } else {
+        game.log
+            .add("No enemy is close enough to strike.", colors::RED);
+        UseResult::Cancelled
+    }
+}
+
+fn cast_detect_traps(
+    _inventory_id: usize,
+    objects: &mut [Object],
+    game: &mut Game,
+    _ui: &mut Ui,
+) -> UseResult {
+    let found = reveal_traps(objects[PLAYER].pos(), DETECT_TRAPS_RADIUS, 1.0, &mut game.map);
+    game.events.emit(GameEvent::TrapsDetected { count: found.len() });
+    UseResult::UsedUp
+}
+
 fn closest_monster(max_range: i32, objects: &mut [Object], ui: &Ui) -> Option<usize> {
     let mut closest_enemy = None;
     let mut closest_dist = (max_range + 1) as f32;
@@ -257,7 +926,7 @@ fn closest_monster(max_range: i32, objects: &mut [Object], ui: &Ui) -> Option<us
     closest_enemy
 }
 
-fn target_tile(
+pub fn target_tile(
     ui: &mut Ui,
     objects: &[Object],
     game: &mut Game,
@@ -274,9 +943,11 @@ fn target_tile(
             None => {}
         }
         render_all(ui, objects, game, false);
-        let (x, y) = (ui.mouse.cx as i32, ui.mouse.cy as i32);
+        let (x, y) = ui.camera
+            .to_map(ui.mouse.cx as i32, ui.mouse.cy as i32)
+            .unwrap_or((-1, -1));
 
-        let in_fov = (x < MAP_WIDTH) && (y < MAP_HEIGHT) && ui.fov.is_in_fov(x, y);
+        let in_fov = game.map.in_bounds(x, y) && ui.fov.is_in_fov(x, y);
         let in_range = max_range.map_or(true, |range| objects[PLAYER].distance(x, y) <= range);
         if ui.mouse.lbutton_pressed && in_fov && in_range {
             return Some((x, y));
@@ -317,26 +988,179 @@ fn toggle_equipment(
         Some(equipment) => equipment,
         None => return UseResult::Cancelled,
     };
+    if is_stuck(&game.inventory[inventory_id], &game.identification, &mut game.log) {
+        return UseResult::Cancelled;
+    }
     if equipment.equipped {
         game.inventory[inventory_id].unequip(&mut game.log);
-    } else {
-        // Unequip old equipment if needed.
-        if let Some(old_equipment) = get_equipment_in_slot(equipment.slot, &game.inventory) {
-            game.inventory[old_equipment].unequip(&mut game.log);
-        }
-        game.inventory[inventory_id].equip(&mut game.log);
+    } else if !equip_item(
+        inventory_id,
+        &mut game.inventory,
+        &game.identification,
+        &mut game.log,
+    ) {
+        return UseResult::Cancelled;
     }
     UseResult::UsedAndKept
 }
 
-fn get_equipment_in_slot(slot: Slot, inventory: &[Object]) -> Option<usize> {
-    for (inventory_id, item) in inventory.iter().enumerate() {
-        if item.equipment
-            .as_ref()
-            .map_or(false, |e| e.equipped && e.slot == slot)
-        {
-            return Some(inventory_id);
+/// Wears down the player's equipped melee weapons after an attack.
+pub fn wear_weapons(player: &mut Object, game: &mut Game) {
+    let weapons: Vec<usize> = (0..game.inventory.len())
+        .filter(|&id| {
+            game.inventory[id].equipment.map_or(false, |e| {
+                e.equipped && e.launcher.is_none() && e.is_weapon()
+            })
+        })
+        .collect();
+    // Back to front, so a broken weapon doesn't shift the ids of the rest.
+    for id in weapons.into_iter().rev() {
+        wear_item(id, player, game);
+    }
+}
+
+/// Wears down one random piece of the player's armour after they are hit.
+pub fn wear_armour(player: &mut Object, game: &mut Game) {
+    let armour: Vec<usize> = (0..game.inventory.len())
+        .filter(|&id| {
+            game.inventory[id].equipment.map_or(false, |e| {
+                e.equipped && !e.is_weapon() && e.max_durability > 0
+            })
+        })
+        .collect();
+    if let Some(&id) = random::rng().choose(&armour) {
+        wear_item(id, player, game);
+    }
+}
+
+/// Takes one point of durability off an inventory item, which breaks at zero.
+fn wear_item(inventory_id: usize, player: &mut Object, game: &mut Game) {
+    let name = game.identification.name_of(&game.inventory[inventory_id]);
+    let (was_damaged, equipment) = match game.inventory[inventory_id].equipment {
+        Some(ref mut equipment) if equipment.max_durability > 0 => {
+            let was_damaged = equipment.is_damaged();
+            equipment.durability -= 1;
+            (was_damaged, *equipment)
+        }
+        _ => return,
+    };
+    if equipment.durability <= 0 {
+        game.inventory.remove(inventory_id);
+        game.events.emit(GameEvent::ItemBroken { name: name });
+        // Losing a max HP bonus can leave the player with more HP than they can have.
+        player.heal(0, game);
+    } else if equipment.is_damaged() && !was_damaged {
+        game.events.emit(GameEvent::ItemDamaged { name: name });
+    }
+}
+
+/// Whether an item is cursed and worn, so it can't be taken off. Tells the player if so.
+pub fn is_stuck(item: &Object, identification: &Identification, log: &mut Messages) -> bool {
+    let stuck = item.equipment.map_or(false, |e| e.equipped && e.cursed);
+    if stuck {
+        log.add(
+            format!(
+                "You can't take off the {}, it is cursed!",
+                identification.name_of(item)
+            ),
+            colors::RED,
+        );
+    }
+    stuck
+}
+
+/// Equips an item from an inventory, taking off whatever is in the way.
+/// Rings go on whichever hand is free. Returns false if a cursed item is in the way.
+pub fn equip_item(
+    inventory_id: usize,
+    inventory: &mut [Object],
+    identification: &Identification,
+    log: &mut Messages,
+) -> bool {
+    let mut equipment = match inventory[inventory_id].equipment {
+        Some(equipment) => equipment,
+        None => return false,
+    };
+    equipment.slot = free_slot(inventory_id, inventory).unwrap_or(equipment.slot);
+    let slots = equipment.slots();
+    let in_the_way: Vec<usize> = (0..inventory.len())
+        .filter(|&id| {
+            id != inventory_id && inventory[id].equipment.map_or(false, |e| {
+                e.equipped && e.slots().iter().any(|slot| slots.contains(slot))
+            })
+        })
+        .collect();
+    if in_the_way
+        .iter()
+        .any(|&id| is_stuck(&inventory[id], identification, log))
+    {
+        return false;
+    }
+
+    inventory[inventory_id].equipment = Some(equipment);
+    for id in in_the_way {
+        inventory[id].unequip(log);
+    }
+    inventory[inventory_id].equip(log);
+    true
+}
+
+/// Puts an item on if that doesn't require taking anything off, without logging it.
+/// Returns whether the item was equipped.
+pub fn equip_if_free(inventory_id: usize, inventory: &mut [Object]) -> bool {
+    let slot = match free_slot(inventory_id, inventory) {
+        Some(slot) => slot,
+        None => return false,
+    };
+    if let Some(ref mut equipment) = inventory[inventory_id].equipment {
+        equipment.slot = slot;
+        equipment.equipped = true;
+    }
+    true
+}
+
+/// Drops everything carried by the dead at a position onto the floor there.
+pub fn drop_loot(x: i32, y: i32, objects: &mut Vec<Object>) {
+    let mut dropped = vec![];
+    for object in objects
+        .iter_mut()
+        .filter(|object| !object.alive && object.pos() == (x, y))
+    {
+        for mut item in object.inventory.drain(..) {
+            if let Some(ref mut equipment) = item.equipment {
+                equipment.equipped = false;
+            }
+            item.set_pos(object.x, object.y);
+            item.always_visible = true;
+            dropped.push(item);
+        }
+    }
+    objects.extend(dropped);
+}
+
+/// A slot the item could be equipped in without taking anything else off.
+fn free_slot(inventory_id: usize, inventory: &[Object]) -> Option<Slot> {
+    let mut equipment = inventory[inventory_id].equipment?;
+    let candidates = Some(equipment.slot)
+        .into_iter()
+        .chain(equipment.slot.alternative());
+    for slot in candidates {
+        equipment.slot = slot;
+        let blocked = equipment
+            .slots()
+            .iter()
+            .any(|&slot| get_equipment_in_slot(slot, inventory).is_some());
+        if !blocked {
+            return Some(slot);
         }
     }
     None
 }
+
+/// The equipped item covering a slot, including two-handed weapons covering the left hand.
+pub fn get_equipment_in_slot(slot: Slot, inventory: &[Object]) -> Option<usize> {
+    inventory.iter().position(|item| {
+        item.equipment
+            .map_or(false, |e| e.equipped && e.slots().contains(&slot))
+    })
+}
@@ -0,0 +1,707 @@
+// Following https://tomassedovic.github.io/roguelike-tutorial/part-5-combat.html
+
+extern crate rand;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+extern crate glutin_window;
+extern crate graphics;
+extern crate opengl_graphics;
+extern crate piston;
+
+pub mod map;
+pub mod object;
+pub mod renderer;
+pub mod item;
+pub mod fov;
+pub mod console;
+pub mod event;
+pub mod data;
+pub mod monster;
+pub mod spawn;
+pub mod vault;
+pub mod trap;
+pub mod camera;
+pub mod random;
+pub mod identify;
+pub mod ranged;
+pub mod affix;
+
+use map::*;
+use object::*;
+use item::*;
+use renderer::{menu, sectioned_menu, MenuSection, MSG_HEIGHT};
+use map::{Map, MAP_HEIGHT, MAP_WIDTH};
+use fov::Fov;
+use camera::Camera;
+use identify::Identification;
+use console::Console;
+use data::{GameData, STARTING_WEAPON};
+use event::{EventBus, EventListener, GameEvent, Statistics};
+use piston::input::*;
+
+use std::collections::HashMap;
+use std::mem;
+
+const SCREEN_WIDTH: i32 = 80;
+const SCREEN_HEIGHT: i32 = 50;
+const LIMIT_FPS: i32 = 20;
+pub const PLAYER: usize = 0;
+const LEVEL_UP_BASE: i32 = 200;
+const LEVEL_UP_FACTOR: i32 = 150;
+const LEVEL_SCREEN_WIDTH: i32 = 40;
+const CHARACTER_SCREEN_WIDTH: i32 = 30;
+const EQUIPMENT_SCREEN_WIDTH: i32 = 40;
+
+type Messages = Vec<(String, [f32; 4])>;
+
+//#[derive(Serialize, Deserialize)]
+pub struct Ui {
+    root: Console,
+    con: Console,
+    panel: Console,
+    fov: Fov,
+    camera: Camera,
+}
+
+//#[derive(Serialize, Deserialize)]
+pub struct Game {
+    map: Map,
+    log: Messages,
+    inventory: Vec<Object>,
+    dungeon_level: u32,
+    player_level: i32,
+    events: EventBus,
+    stats: Statistics,
+    data: GameData,
+    identification: Identification,
+    /// Levels the player has left, by dungeon level.
+    levels: HashMap<u32, SavedLevel>,
+}
+
+/// A level the player has left, kept so it can be restored exactly as it was.
+pub struct SavedLevel {
+    map: Map,
+    /// Everything on the level except the player.
+    objects: Vec<Object>,
+}
+
+/// Runs the game in a window. Also handles the command line options of the game binary.
+pub fn run() {
+    let data = match GameData::load() {
+        Ok(data) => data,
+        Err(err) => {
+            eprintln!("Failed to load game data: {}", err);
+            std::process::exit(1);
+        }
+    };
+
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() == 3 && args[1] == "--spawn-table" {
+        match args[2].parse() {
+            Ok(level) => print_spawn_table(&data, level),
+            Err(_) => eprintln!("Invalid dungeon level: {}", args[2]),
+        }
+        return;
+    }
+
+    let root = Console::initializer()
+        .font("arial10x10.png", FontLayout::Ui)
+        .font_type(FontType::Greyscale)
+        .size(SCREEN_WIDTH, SCREEN_HEIGHT)
+        .title("Rust/libui tutorial")
+        .init();
+    ui::system::set_fps(LIMIT_FPS);
+
+    let mut ui = Ui {
+        root: Console::new(SCREEN_WIDTH, SCREEN_HEIGHT),
+        con: Console::new(SCREEN_WIDTH, SCREEN_HEIGHT),
+        panel: Console::new(SCREEN_WIDTH, renderer::PANEL_HEIGHT),
+        fov: Fov::new(MAP_WIDTH, MAP_HEIGHT),
+        camera: Camera::new(renderer::VIEW_WIDTH, renderer::VIEW_HEIGHT),
+    };
+
+    main_menu(&mut ui, &data);
+}
+
+/// Prints the resolved spawn probabilities for a dungeon level, for tuning the data files.
+fn print_spawn_table(data: &GameData, level: u32) {
+    println!("Dungeon level {}", level);
+    println!("Monsters per room: 0-{}", map::max_monsters(level));
+    println!("{}", data.monster_table().describe(level));
+    println!("Items per room: 0-{}", map::max_items(level));
+    println!("{}", data.item_table().describe(level));
+}
+
+fn main_menu(ui: &mut Ui, data: &GameData) {
+    while !ui.root.window_closed() {
+        ui.root.set_default_foreground(colors::LIGHT_YELLOW);
+        ui.root.print_centered(
+            SCREEN_WIDTH / 2,
+            SCREEN_HEIGHT / 2 - 4,
+            "TOMBS OF THE ANCIENT KINGS",
+        );
+        ui.root.print_centered(
+            SCREEN_WIDTH / 2,
+            SCREEN_HEIGHT - 2,
+            "Luringen",
+        );
+
+        let choices = &["Play a new game", "Continue last game", "Quit"];
+        let choice = menu("", choices, 24, &mut ui.root);
+        match choice {
+            Some(0) => {
+                match new_game(ui, data) {
+                    Ok((mut objects, mut game)) => play_game(&mut objects, &mut game, ui),
+                    Err(err) => msgbox(
+                        &format!("Could not generate the dungeon: {}.\n", err),
+                        CHARACTER_SCREEN_WIDTH,
+                        &mut ui.root,
+                    ),
+                }
+            }
+            Some(2) => break,
+            _ => {}
+        }
+    }
+}
+
+fn play_game(objects: &mut Vec<Object>, game: &mut Game, ui: &mut Ui) {
+    let mut fov_recompute = true;
+    let mut key = Default::default();
+
+    // Main loop.
+    while !ui.root.window_closed() {
+        match input::check_for_event(input::MOUSE | input::KEY_PRESS) {
+            Some((_, Event::Mouse(m))) => ui.mouse = m,
+            Some((_, Event::Key(k))) => key = k,
+            _ => key = Default::default(),
+        }
+
+        renderer::render_all(ui, &objects, game, fov_recompute);
+
+        ui.root.flush();
+
+        level_up(objects, game, ui);
+
+        for object in objects.iter_mut() {
+            object.clear(&mut ui.con, &ui.camera);
+        }
+
+        let previous_player_position = objects[PLAYER].pos();
+        let player_action = handle_keys(key, ui, objects, game);
+        if player_action == PlayerAction::Exit {
+            break;
+        }
+
+        // Walking onto slow terrain gives the monsters extra turns.
+        let (player_x, player_y) = objects[PLAYER].pos();
+        let moved = (player_x, player_y) != previous_player_position;
+        let turns = if moved {
+            game.map[(player_x, player_y)].kind.movement_cost()
+        } else {
+            1
+        };
+        if objects[PLAYER].alive && player_action != PlayerAction::DidntTakeTurn {
+            for _ in 0..turns {
+                for id in 0..objects.len() {
+                    if objects[id].ai.is_some() {
+                        ai_take_turn(id, objects, game, &ui.fov);
+                    }
+                }
+            }
+        }
+        fov_recompute = moved || player_action == PlayerAction::TookTurn;
+
+        process_events(objects, game);
+    }
+}
+
+/// Drains the event queue, keeping the message log, statistics and player XP in sync.
+fn process_events(objects: &mut Vec<Object>, game: &mut Game) {
+    while let Some(event) = game.events.next() {
+        game.log.on_event(&event);
+        game.stats.on_event(&event);
+        match event {
+            GameEvent::XpGained { amount } => {
+                if let Some(fighter) = objects[PLAYER].fighter.as_mut() {
+                    fighter.xp += amount;
+                }
+            }
+            GameEvent::MonsterDied { x, y, .. } => drop_loot(x, y, objects),
+            _ => {}
+        }
+    }
+}
+
+fn new_game(ui: &mut Ui, data: &GameData) -> Result<(Vec<Object>, Game), MapError> {
+    let mut player = Object::new(0, 0, '@', "player", colors::WHITE, true);
+    player.alive = true;
+    player.fighter = Some(Fighter {
+        base_max_hp: 100,
+        hp: 100,
+        base_defense: 1,
+        base_power: 2,
+        on_death: DeathCallback::Player,
+        xp: 0,
+        resistances: Resistances::default(),
+    });
+
+    let mut objects = vec![player];
+    let mut game = Game {
+        map: make_map(&mut objects, 1, data)?,
+        log: vec![],
+        inventory: vec![],
+        dungeon_level: 1,
+        player_level: 1,
+        events: EventBus::new(),
+        stats: Statistics::default(),
+        data: data.clone(),
+        identification: Identification::new(&data.items),
+        levels: HashMap::new(),
+    };
+
+    let template = data.item(STARTING_WEAPON)
+        .expect("starting weapon is checked when loading data");
+    let mut dagger = template.spawn(0, 0);
+    // The starting weapon is never enchanted or cursed.
+    dagger.equipment = template.equipment.map(|equipment| Equipment {
+        equipped: true,
+        identified: true,
+        durability: equipment.max_durability,
+        ..equipment
+    });
+    game.inventory.push(dagger);
+
+    initialize_fov(&game.map, ui);
+
+    game.log.add(
+        "Welcome stranger! Prepare to perish in the Tombs of the Ancient Kings.",
+        colors::RED,
+    );
+
+    Ok((objects, game))
+}
+
+fn initialize_fov(map: &Map, ui: &mut Ui) {
+    ui.fov = Fov::from_map(map);
+    ui.con.clear(); // Clear out previous FOV.
+}
+
+/// Handles keyboard input and returns whether or not
+/// the application should exit.
+fn handle_keys(
+    key: Key,
+    ui: &mut Ui,
+    objects: &mut Vec<Object>,
+    game: &mut Game,
+    btn: &Button,
+) -> PlayerAction {
+    use PlayerAction::*;
+
+    let player_alive = objects[PLAYER].alive;
+    match (btn, player_alive) {
+        (&Button::Keyboard(Key::NumPad8), true) | (&Button::Keyboard(Key::Up), true) => {
+            player_move_or_attack(PLAYER, 0, -1, objects, game, &ui.fov);
+            TookTurn
+        }
+        (&Button::Keyboard(Key::NumPad2), true) | (&Button::Keyboard(Key::Down), true) => {
+            player_move_or_attack(PLAYER, 0, 1, objects, game, &ui.fov);
+            TookTurn
+        }
+        (&Button::Keyboard(Key::NumPad4), true) | (&Button::Keyboard(Key::Left), true) => {
+            player_move_or_attack(PLAYER, -1, 0, objects, game, &ui.fov);
+            TookTurn
+        }
+        (&Button::Keyboard(Key::NumPad6), true) | (&Button::Keyboard(Key::Right), true) => {
+            player_move_or_attack(PLAYER, 1, 0, objects, game, &ui.fov);
+            TookTurn
+        }
+        (&Button::Keyboard(Key::NumPad7), true) => {
+            player_move_or_attack(PLAYER, -1, -1, objects, game, &ui.fov);
+            TookTurn
+        }
+        (&Button::Keyboard(Key::NumPad9), true) => {
+            player_move_or_attack(PLAYER, 1, -1, objects, game, &ui.fov);
+            TookTurn
+        }
+        (&Button::Keyboard(Key::NumPad3), true) => {
+            player_move_or_attack(PLAYER, 1, 1, objects, game, &ui.fov);
+            TookTurn
+        }
+        (&Button::Keyboard(Key::NumPad1), true) => {
+            player_move_or_attack(PLAYER, -1, 1, objects, game, &ui.fov);
+            TookTurn
+        }
+        (&Button::Keyboard(Key::NumPad5), true) => TookTurn,
+        (&Button::Keyboard(Key::End), true) => TookTurn,
+        (&Button::Keyboard(Key::S), true) => {
+            trap::search(objects, game);
+            TookTurn
+        }
+        (&Button::Keyboard(Key::X), true) => {
+            let (x, y) = objects[PLAYER].pos();
+            if close_adjacent_door(x, y, &mut game.map, objects) {
+                TookTurn
+            } else {
+                game.log.add("There is no open door to close.", colors::WHITE);
+                DidntTakeTurn
+            }
+        }
+        (&Button::Keyboard(Key::G), true) => {
+            let item_id = objects
+                .iter()
+                .position(|object| object.pos() == objects[PLAYER].pos() && object.item.is_some());
+            if let Some(item_id) = item_id {
+                pick_item_up(item_id, objects, game);
+            }
+            DidntTakeTurn
+        }
+        (&Button::Keyboard(Key::I), true) => {
+            let inventory_index = inventory_menu(
+                &game.inventory,
+                &game.identification,
+                "Press the key next to an item to use it, or any other to cancel.\n",
+                &mut ui.root,
+            );
+            if let Some(inventory_index) = inventory_index {
+                use_item(inventory_index, objects, ui, game);
+            }
+            DidntTakeTurn
+        }
+        (&Button::Keyboard(Key::D), true) => {
+            let inventory_index = inventory_menu(
+                &game.inventory,
+                &game.identification,
+                "Press the key next to an item to drop it, or any other to cancel.\n",
+                &mut ui.root,
+            );
+            if let Some(inventory_index) = inventory_index {
+                let count = match game.inventory[inventory_index].quantity {
+                    Some(quantity) if quantity > 1 => {
+                        renderer::quantity_prompt("Drop how many?", quantity, &mut ui.root)
+                    }
+                    _ => Some(1),
+                };
+                if let Some(count) = count {
+                    drop_item(inventory_index, count, objects, game);
+                }
+            }
+            DidntTakeTurn
+        }
+        (&Button::Keyboard(Key::T), true) => {
+            let inventory_index = inventory_menu(
+                &game.inventory,
+                &game.identification,
+                "Press the key next to an item to throw it, or any other to cancel.\n",
+                &mut ui.root,
+            );
+            match inventory_index {
+                Some(inventory_index) if ranged::throw_item(inventory_index, objects, ui, game) => {
+                    TookTurn
+                }
+                _ => DidntTakeTurn,
+            }
+        }
+        (&Button::Keyboard(Key::F), true) => if ranged::fire(objects, ui, game) {
+            TookTurn
+        } else {
+            DidntTakeTurn
+        },
+        (&Button::Keyboard(Key::Less), true) => {
+            let player_on_stairs = objects
+                .iter()
+                .any(|object| object.pos() == objects[PLAYER].pos() && object.name == "stairs");
+            if player_on_stairs {
+                next_level(game, objects, ui);
+            }
+            DidntTakeTurn
+        }
+        (&Button::Keyboard(Key::Greater), true) => {
+            let player_on_stairs = objects
+                .iter()
+                .any(|object| object.pos() == objects[PLAYER].pos() && object.name == "up stairs");
+            if player_on_stairs {
+                previous_level(game, objects, ui);
+            }
+            DidntTakeTurn
+        }
+        (&Button::Keyboard(Key::E), true) => {
+            msgbox(
+                &equipment_screen(&game.inventory, &game.identification),
+                EQUIPMENT_SCREEN_WIDTH,
+                &mut ui.root,
+            );
+            DidntTakeTurn
+        }
+        (&Button::Keyboard(Key::C), true) => {
+            let player = &objects[PLAYER];
+            let level = game.player_level;
+            let level_up_xp = LEVEL_UP_BASE + level * LEVEL_UP_FACTOR;
+            if let Some(fighter) = player.fighter.as_ref() {
+                let msg = format!(
+                    "Character information
+Level: {}
+Experience: {}
+Experience to level up: {}
+
+Maximum HP: {}
+Attack: {}
+Defense: {}
+
+Monsters slain: {}",
+                    level,
+                    fighter.xp,
+                    level_up_xp,
+                    player.max_hp(game),
+                    player.power(game),
+                    player.defense(game),
+                    game.stats.kills
+                );
+                msgbox(&msg, CHARACTER_SCREEN_WIDTH, &mut ui.root);
+            }
+            DidntTakeTurn
+        }
+        (
+            Key {
+                code: Enter,
+                alt: true,
+                ..
+            },
+            _,
+        ) => {
+            let fullscreen = ui.root.is_fullscreen();
+            ui.root.set_fullscreen(!fullscreen);
+            DidntTakeTurn
+        }
+        (Key { code: Escape, .. }, _) => Exit,
+        _ => DidntTakeTurn,
+    }
+}
+
+fn inventory_menu(
+    inventory: &[Object],
+    identification: &Identification,
+    header: &str,
+    root: &mut Console,
+) -> Option<usize> {
+    if inventory.len() == 0 {
+        let text = format!("{}\n\nInventory is empty.", header);
+        msgbox(&text, renderer::INVENTORY_WIDTH, root);
+        return None;
+    }
+
+    let sections: Vec<MenuSection<String>> = ALL_CATEGORIES
+        .iter()
+        .map(|&category| MenuSection {
+            title: category.to_string(),
+            options: inventory
+                .iter()
+                .enumerate()
+                .filter(|&(_, item)| item_category(item) == category)
+                .map(|(id, item)| {
+                    let color = affix::rarity_color(item).unwrap_or(colors::WHITE);
+                    (id, describe_item(item, identification), color)
+                })
+                .collect(),
+        })
+        .collect();
+    sectioned_menu(header, &sections, renderer::INVENTORY_WIDTH, root)
+}
+
+/// How an item is listed in the inventory.
+fn describe_item(item: &Object, identification: &Identification) -> String {
+    let mut text = identification.name_of(item);
+    match item.quantity {
+        Some(quantity) if quantity > 1 => text.push_str(&format!(" (x{})", quantity)),
+        _ => {}
+    }
+    match item.charges {
+        Some(charges) if identification.is_known(item) => {
+            text.push_str(&format!(" ({}/{} charges)", charges.current, charges.max))
+        }
+        _ => {}
+    }
+    match item.equipment {
+        Some(equipment) if equipment.equipped => {
+            text.push_str(&format!(" (on {})", equipment.worn_on()))
+        }
+        _ => {}
+    }
+    match item.equipment {
+        Some(equipment) if equipment.max_durability > 0 => {
+            let condition = if equipment.is_damaged() {
+                "damaged"
+            } else {
+                "durability"
+            };
+            text.push_str(&format!(
+                " ({} {}/{})",
+                condition, equipment.durability, equipment.max_durability
+            ))
+        }
+        _ => {}
+    }
+    text
+}
+
+/// Lists what the player is wearing in each slot.
+fn equipment_screen(inventory: &[Object], identification: &Identification) -> String {
+    let mut text = String::from("Equipment\n");
+    for &slot in ALL_SLOTS.iter() {
+        let worn = get_equipment_in_slot(slot, inventory)
+            .map_or("-".into(), |id| identification.name_of(&inventory[id]));
+        text.push_str(&format!("\n{:<18} {}", format!("{}:", slot), worn));
+    }
+    text
+}
+
+trait MessageLog {
+    fn add<T: Into<String>>(&mut self, message: T, color: [f32; 4]);
+}
+
+impl MessageLog for Vec<(String, [f32; 4])> {
+    fn add<T: Into<String>>(&mut self, message: T, color: [f32; 4]) {
+        if self.len() == MSG_HEIGHT {
+            self.remove(0);
+        }
+        self.push((message.into(), color));
+    }
+}
+
+fn next_level(game: &mut Game, objects: &mut Vec<Object>, ui: &mut Ui) {
+    let target = game.dungeon_level + 1;
+    if game.levels.contains_key(&target) {
+        game.log.add("You descend the stairs once more.", colors::RED);
+        enter_saved_level(target, "up stairs", game, objects, ui);
+        return;
+    }
+
+    game.log.add(
+        "You take a moment to rest, and recover your strength.",
+        colors::VIOLET,
+    );
+    let heal_hp = objects[PLAYER].max_hp(game) / 2;
+    objects[PLAYER].heal(heal_hp, game);
+
+    let current_objects = objects.split_off(PLAYER + 1);
+    match make_map(objects, target, &game.data) {
+        Ok(map) => {
+            game.log.add(
+                "After a rare moment of peace, you descend deeper into the heart of the dungeon...",
+                colors::RED,
+            );
+            let current_map = mem::replace(&mut game.map, map);
+            game.levels.insert(
+                game.dungeon_level,
+                SavedLevel {
+                    map: current_map,
+                    objects: current_objects,
+                },
+            );
+            game.dungeon_level = target;
+            initialize_fov(&game.map, ui);
+        }
+        Err(err) => {
+            objects.extend(current_objects);
+            game.log.add(
+                format!("The way down is blocked ({}).", err),
+                colors::RED,
+            );
+        }
+    }
+}
+
+fn previous_level(game: &mut Game, objects: &mut Vec<Object>, ui: &mut Ui) {
+    let target = game.dungeon_level - 1;
+    if game.levels.contains_key(&target) {
+        game.log.add("You climb back up the stairs.", colors::RED);
+        enter_saved_level(target, "stairs", game, objects, ui);
+    }
+}
+
+/// Stores the current level and restores a visited one, placing the player
+/// on the object with the given name, usually the stairs they came through.
+fn enter_saved_level(
+    target: u32,
+    arrive_on: &str,
+    game: &mut Game,
+    objects: &mut Vec<Object>,
+    ui: &mut Ui,
+) {
+    let saved = match game.levels.remove(&target) {
+        Some(saved) => saved,
+        None => return,
+    };
+
+    let current_objects = objects.split_off(PLAYER + 1);
+    let current_map = mem::replace(&mut game.map, saved.map);
+    game.levels.insert(
+        game.dungeon_level,
+        SavedLevel {
+            map: current_map,
+            objects: current_objects,
+        },
+    );
+
+    objects.extend(saved.objects);
+    let arrival = objects
+        .iter()
+        .find(|object| object.name == arrive_on)
+        .map(|object| object.pos());
+    if let Some((x, y)) = arrival {
+        objects[PLAYER].set_pos(x, y);
+    }
+    game.dungeon_level = target;
+    initialize_fov(&game.map, ui);
+}
+
+fn level_up(objects: &mut [Object], game: &mut Game, ui: &mut Ui) {
+    let player = &mut objects[PLAYER];
+    let level_up_xp = LEVEL_UP_BASE + game.player_level * LEVEL_UP_FACTOR;
+    if player.fighter.as_ref().map_or(0, |f| f.xp) >= level_up_xp {
+        game.player_level += 1;
+        game.log.add(
+            format!(
+                "Your battle skills grow stronger! You reached level {}!",
+                game.player_level
+            ),
+            colors::YELLOW,
+        );
+
+        let fighter = player.fighter.as_mut().unwrap();
+        let mut choice = None;
+        while choice.is_none() {
+            choice = menu(
+                "Level up! Choose a stat to raise:\n",
+                &[
+                    format!("Constitution (+20 HP, from {})", fighter.base_max_hp),
+                    format!("Strength (+1 attack, from {})", fighter.base_power),
+                    format!("Agility (+1 defense, from {})", fighter.base_defense),
+                ],
+                LEVEL_SCREEN_WIDTH,
+                &mut ui.root,
+            );
+        }
+        fighter.xp -= level_up_xp;
+        match choice.unwrap() {
+            0 => {
+                fighter.base_max_hp += 20;
+                fighter.hp += 20;
+            }
+            1 => {
+                fighter.base_power += 1;
+            }
+            2 => {
+                fighter.base_defense += 1;
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+fn msgbox(text: &str, width: i32, root: &mut Console) {
+    let options: &[&str] = &[];
+    menu(text, options, width, root);
+}
@@ -1,29 +1,87 @@
 use object::*;
-use item::*;
 
 use std::cmp;
-use rand;
+use std::collections::VecDeque;
+use std::ops::{Index, IndexMut};
 use rand::Rng;
-use rand::distributions::{IndependentSample, Weighted, WeightedChoice};
+use random;
 
-pub const MAP_WIDTH: i32 = 80;
-pub const MAP_HEIGHT: i32 = 43;
+/// Size of newly generated levels.
+/// Levels are larger than the view, which scrolls to follow the player.
+pub const MAP_WIDTH: i32 = 120;
+pub const MAP_HEIGHT: i32 = 64;
 
 const ROOM_MAX_SIZE: i32 = 10;
 const ROOM_MIN_SIZE: i32 = 6;
-const MAX_ROOMS: i32 = 30;
+const MAX_ROOMS: i32 = 60;
+const BSP_MIN_LEAF_SIZE: i32 = ROOM_MIN_SIZE + 2;
 
-use PLAYER;
+const CAVE_WALL_CHANCE: f32 = 0.45;
+const CAVE_SMOOTHING_STEPS: i32 = 5;
+const CAVE_MIN_OPEN_TILES: usize = 600;
+/// Cave levels get as many monsters and items as a room for every this many open tiles.
+const CAVE_TILES_PER_AREA: usize = 80;
+const CAVE_SAFE_RADIUS: f32 = 6.0;
 
-pub type Map = Vec<Vec<Tile>>;
+use data::GameData;
+use spawn::{value_at, Interpolation, Transition};
+use vault::{VaultSpawn, VAULT_DOOR, VAULT_KEEP, VAULT_WALL};
+use trap::{Trap, TrapKind};
+use affix::roll_affixes;
+use PLAYER;
 
-struct Transition {
-    level: u32,
-    value: u32,
+/// The tiles of a level, indexed by `(x, y)`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Map {
+    width: i32,
+    height: i32,
+    tiles: Vec<Tile>,
+    /// Rooms laid out by the generator. Cave levels have none.
+    #[serde(default)]
+    rooms: Vec<Rect>,
 }
 
+const MAX_MONSTERS_PER_ROOM: &[Transition] = &[
+    Transition { level: 1, value: 2 },
+    Transition { level: 4, value: 3 },
+    Transition { level: 6, value: 5 },
+];
+const MAX_ITEMS_PER_ROOM: &[Transition] = &[
+    Transition { level: 1, value: 1 },
+    Transition { level: 4, value: 2 },
+];
+const MAX_TRAPS: &[Transition] = &[
+    Transition { level: 1, value: 2 },
+    Transition { level: 3, value: 4 },
+    Transition { level: 6, value: 6 },
+];
+/// Traps are kept at least this far away from where the player arrives.
+const TRAP_SAFE_RADIUS: f32 = 3.0;
+const MAX_PLACEMENT_ATTEMPTS: i32 = 20;
+const MAX_VAULT_ATTEMPTS: i32 = 200;
+const MAX_GENERATION_ATTEMPTS: i32 = 10;
+/// Smallest number of reachable tiles a level may have.
+const MIN_OPEN_TILES: usize = 100;
+/// One extra tunnel is dug for every this many rooms.
+const LOOP_EVERY_N_ROOMS: usize = 4;
+
+const COLOR_DARK_WALL: [f32; 4] = [0.0, 0.0, 0.39, 1.0];
+const COLOR_LIGHT_WALL: [f32; 4] = [0.51, 0.43, 0.2, 1.0];
+const COLOR_DARK_GROUND: [f32; 4] = [0.2, 0.2, 0.59, 1.0];
+const COLOR_LIGHT_GROUND: [f32; 4] = [0.78, 0.7, 0.2, 1.0];
+
+const LAVA_DAMAGE: i32 = 15;
+const DOOR_CHANCE: f32 = 0.6;
+const RUBBLE_CHANCE: f32 = 0.02;
+const TREE_CHANCE: f32 = 0.04;
+const CAVE_POOLS: i32 = 4;
+const CAVE_POOL_MAX_RADIUS: i32 = 4;
+/// Lava pools only appear from this dungeon level on.
+const LAVA_MIN_LEVEL: u32 = 4;
+const LAVA_POOL_CHANCE: f32 = 0.3;
+
 pub fn is_blocked(x: i32, y: i32, map: &Map, objects: &[Object]) -> bool {
-    if map[x as usize][y as usize].blocked {
+    if map.get(x, y).map_or(true, Tile::blocked) {
         return true;
     }
 
@@ -32,74 +90,405 @@ pub fn is_blocked(x: i32, y: i32, map: &Map, objects: &[Object]) -> bool {
         .any(|object| object.blocks && object.pos() == (x, y))
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum TileKind {
+    Floor,
+    Wall,
+    DoorClosed,
+    DoorOpen,
+    ShallowWater,
+    DeepWater,
+    Lava,
+    Rubble,
+    Tree,
+}
+
 #[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct Tile {
-    pub blocked: bool,
-    pub block_sight: bool,
+    pub kind: TileKind,
     pub explored: bool,
+    #[serde(default)]
+    pub trap: Option<Trap>,
+}
+
+impl TileKind {
+    pub fn blocks_movement(self) -> bool {
+        match self {
+            TileKind::Wall | TileKind::DoorClosed => true,
+            _ => false,
+        }
+    }
+
+    pub fn blocks_sight(self) -> bool {
+        match self {
+            TileKind::Wall | TileKind::DoorClosed | TileKind::Tree => true,
+            _ => false,
+        }
+    }
+
+    /// Whether a path can lead through this tile, counting doors that can be opened.
+    pub fn passable(self) -> bool {
+        self != TileKind::Wall
+    }
+
+    /// Whether a path can lead through this tile without risking lava or deep water.
+    pub fn safely_passable(self) -> bool {
+        self.passable() && !self.is_hazard()
+    }
+
+    /// How many turns it takes to walk onto this tile.
+    pub fn movement_cost(self) -> i32 {
+        match self {
+            TileKind::Rubble | TileKind::ShallowWater | TileKind::Tree => 2,
+            TileKind::DeepWater => 3,
+            _ => 1,
+        }
+    }
+
+    /// Damage dealt to anything that walks onto this tile.
+    pub fn damage_on_entry(self) -> Option<(i32, DamageType)> {
+        match self {
+            TileKind::Lava => Some((LAVA_DAMAGE, DamageType::Fire)),
+            _ => None,
+        }
+    }
+
+    /// Whether the player should not be placed on this tile when a level starts.
+    pub fn is_hazard(self) -> bool {
+        self.damage_on_entry().is_some() || self == TileKind::DeepWater
+    }
+
+    pub fn glyph(self) -> char {
+        match self {
+            TileKind::Floor | TileKind::Wall => ' ',
+            TileKind::DoorClosed => '+',
+            TileKind::DoorOpen => '\'',
+            TileKind::ShallowWater | TileKind::DeepWater | TileKind::Lava => '~',
+            TileKind::Rubble => ':',
+            TileKind::Tree => '\u{2663}',
+        }
+    }
+
+    /// Foreground and background colours, lit or out of sight.
+    pub fn colors(self, visible: bool) -> ([f32; 4], [f32; 4]) {
+        let (foreground, background) = match self {
+            TileKind::Wall if visible => return (COLOR_LIGHT_WALL, COLOR_LIGHT_WALL),
+            TileKind::Wall => return (COLOR_DARK_WALL, COLOR_DARK_WALL),
+            TileKind::Floor if visible => return (COLOR_LIGHT_GROUND, COLOR_LIGHT_GROUND),
+            TileKind::Floor => return (COLOR_DARK_GROUND, COLOR_DARK_GROUND),
+            TileKind::DoorClosed | TileKind::DoorOpen => ([0.55, 0.35, 0.1, 1.0], COLOR_LIGHT_GROUND),
+            TileKind::Rubble => ([0.45, 0.45, 0.45, 1.0], COLOR_LIGHT_GROUND),
+            TileKind::Tree => ([0.1, 0.5, 0.1, 1.0], COLOR_LIGHT_GROUND),
+            TileKind::ShallowWater => ([0.5, 0.7, 1.0, 1.0], [0.2, 0.4, 0.8, 1.0]),
+            TileKind::DeepWater => ([0.8, 0.9, 1.0, 1.0], [0.0, 0.15, 0.6, 1.0]),
+            TileKind::Lava => ([1.0, 0.8, 0.0, 1.0], [0.8, 0.2, 0.0, 1.0]),
+        };
+        if visible {
+            (foreground, background)
+        } else {
+            (darken(foreground), darken(background))
+        }
+    }
+}
+
+/// Shifts a colour towards the dark blue used for tiles out of sight.
+fn darken(color: [f32; 4]) -> [f32; 4] {
+    [color[0] * 0.4, color[1] * 0.4, color[2] * 0.4 + 0.2, color[3]]
 }
 
 impl Tile {
-    pub fn empty() -> Self {
+    pub fn new(kind: TileKind) -> Self {
         Tile {
-            blocked: false,
-            block_sight: false,
+            kind: kind,
             explored: false,
+            trap: None,
         }
     }
 
+    pub fn empty() -> Self {
+        Tile::new(TileKind::Floor)
+    }
+
     pub fn wall() -> Self {
-        Tile {
-            blocked: true,
-            block_sight: true,
-            explored: false,
+        Tile::new(TileKind::Wall)
+    }
+
+    pub fn blocked(&self) -> bool {
+        self.kind.blocks_movement()
+    }
+
+    pub fn block_sight(&self) -> bool {
+        self.kind.blocks_sight()
+    }
+}
+
+impl Map {
+    /// Creates a map of the given size, filled with copies of a tile.
+    pub fn new(width: i32, height: i32, tile: Tile) -> Self {
+        assert!(width > 0 && height > 0, "map must not be empty");
+        Map {
+            width: width,
+            height: height,
+            tiles: vec![tile; (width * height) as usize],
+            rooms: vec![],
+        }
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    pub fn in_bounds(&self, x: i32, y: i32) -> bool {
+        x >= 0 && y >= 0 && x < self.width && y < self.height
+    }
+
+    /// Whether a position is on the outermost ring of the map.
+    pub fn on_border(&self, x: i32, y: i32) -> bool {
+        x == 0 || y == 0 || x == self.width - 1 || y == self.height - 1
+    }
+
+    /// The tile at a position, or `None` outside of the map.
+    pub fn get(&self, x: i32, y: i32) -> Option<&Tile> {
+        if self.in_bounds(x, y) {
+            Some(&self.tiles[self.offset(x, y)])
+        } else {
+            None
+        }
+    }
+
+    pub fn get_mut(&mut self, x: i32, y: i32) -> Option<&mut Tile> {
+        if self.in_bounds(x, y) {
+            let offset = self.offset(x, y);
+            Some(&mut self.tiles[offset])
+        } else {
+            None
         }
     }
+
+    pub fn rooms(&self) -> &[Rect] {
+        &self.rooms
+    }
+
+    /// Every tile, column by column.
+    pub fn tiles(&self) -> &[Tile] {
+        &self.tiles
+    }
+
+    /// A grid of flags the size of this map, for bookkeeping during generation.
+    fn flags(&self) -> Vec<Vec<bool>> {
+        vec![vec![false; self.height as usize]; self.width as usize]
+    }
+
+    fn offset(&self, x: i32, y: i32) -> usize {
+        (x * self.height + y) as usize
+    }
+}
+
+impl Index<(i32, i32)> for Map {
+    type Output = Tile;
+
+    /// Panics when the position is outside of the map.
+    fn index(&self, (x, y): (i32, i32)) -> &Tile {
+        let (width, height) = (self.width, self.height);
+        self.get(x, y).unwrap_or_else(|| {
+            panic!("({}, {}) is outside of the {}x{} map", x, y, width, height)
+        })
+    }
 }
 
-pub fn make_map(objects: &mut Vec<Object>, level: u32) -> Map {
-    let mut map = vec![vec![Tile::wall(); MAP_HEIGHT as usize]; MAP_WIDTH as usize];
+impl IndexMut<(i32, i32)> for Map {
+    fn index_mut(&mut self, (x, y): (i32, i32)) -> &mut Tile {
+        let (width, height) = (self.width, self.height);
+        self.get_mut(x, y).unwrap_or_else(|| {
+            panic!("({}, {}) is outside of the {}x{} map", x, y, width, height)
+        })
+    }
+}
 
-    // Delete all objects except the player.
+/// Closes an open door next to the given position, if nothing is standing in it.
+/// Returns whether a door was closed.
+pub fn close_adjacent_door(x: i32, y: i32, map: &mut Map, objects: &[Object]) -> bool {
+    for dx in -1..2 {
+        for dy in -1..2 {
+            let (door_x, door_y) = (x + dx, y + dy);
+            let occupied = objects.iter().any(|object| object.pos() == (door_x, door_y));
+            if let Some(tile) = map.get_mut(door_x, door_y) {
+                if tile.kind == TileKind::DoorOpen && !occupied {
+                    tile.kind = TileKind::DoorClosed;
+                    return true;
+                }
+            }
+        }
+    }
+    false
+}
+
+/// Algorithm used to lay out the rooms of a level.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Generator {
+    /// Rooms placed at random, skipping any that overlap.
+    Rooms,
+    /// Binary space partitioning, with one room per leaf.
+    Bsp,
+    /// Natural caves grown with cellular automata.
+    Caves,
+}
+
+impl Generator {
+    /// The generator used for a dungeon level.
+    pub fn for_level(level: u32) -> Generator {
+        match level % 3 {
+            1 => Generator::Rooms,
+            2 => Generator::Bsp,
+            _ => Generator::Caves,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MapError {
+    /// No room could be placed.
+    NoRooms,
+    /// Too little of the map is open to make a playable level.
+    TooSmall,
+    /// Part of the level, or the stairs, cannot be reached from the player.
+    Unreachable,
+    /// A vault that must appear on this level could not be placed.
+    VaultMissing,
+}
+
+impl std::fmt::Display for MapError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            MapError::NoRooms => write!(f, "no rooms could be placed"),
+            MapError::TooSmall => write!(f, "too little open space"),
+            MapError::Unreachable => write!(f, "parts of the level are unreachable"),
+            MapError::VaultMissing => write!(f, "a guaranteed vault could not be placed"),
+        }
+    }
+}
+
+pub fn make_map(objects: &mut Vec<Object>, level: u32, data: &GameData) -> Result<Map, MapError> {
+    make_map_with(objects, level, Generator::for_level(level), (MAP_WIDTH, MAP_HEIGHT), data)
+}
+
+/// Generates a level, retrying until one passes validation.
+/// On failure the objects are left as they were.
+pub fn make_map_with(
+    objects: &mut Vec<Object>,
+    level: u32,
+    generator: Generator,
+    size: (i32, i32),
+    data: &GameData,
+) -> Result<Map, MapError> {
     assert_eq!(&objects[PLAYER] as *const _, &objects[0] as *const _);
-    objects.truncate(1);
+    let previous_objects = objects.split_off(PLAYER + 1);
+    let (previous_x, previous_y) = objects[PLAYER].pos();
 
-    let mut rooms = vec![];
-    for _ in 0..MAX_ROOMS {
-        let w = rand::thread_rng().gen_range(ROOM_MIN_SIZE, ROOM_MAX_SIZE + 1);
-        let h = rand::thread_rng().gen_range(ROOM_MIN_SIZE, ROOM_MAX_SIZE + 1);
-        let x = rand::thread_rng().gen_range(0, MAP_WIDTH - w);
-        let y = rand::thread_rng().gen_range(0, MAP_HEIGHT - h);
-        let new_room = Rect::new(x, y, w, h);
+    let mut error = MapError::NoRooms;
+    for _ in 0..MAX_GENERATION_ATTEMPTS {
+        // Delete all objects except the player.
+        objects.truncate(PLAYER + 1);
+        match try_make_map(objects, level, generator, size, data) {
+            Ok(map) => return Ok(map),
+            Err(e) => error = e,
+        }
+    }
 
-        let failed = rooms
-            .iter()
-            .any(|other_room| new_room.intersects_with(other_room));
-        if !failed {
-            create_room(&new_room, &mut map);
-            place_objects(&new_room, &map, objects, level);
+    objects.truncate(PLAYER + 1);
+    objects.extend(previous_objects);
+    objects[PLAYER].set_pos(previous_x, previous_y);
+    Err(error)
+}
 
-            let (new_x, new_y) = new_room.center();
-            if rooms.is_empty() {
-                objects[PLAYER].set_pos(new_x, new_y);
+fn try_make_map(
+    objects: &mut Vec<Object>,
+    level: u32,
+    generator: Generator,
+    (width, height): (i32, i32),
+    data: &GameData,
+) -> Result<Map, MapError> {
+    let mut map = Map::new(width, height, Tile::wall());
+
+    let (stairs_x, stairs_y) = match generator {
+        Generator::Rooms | Generator::Bsp => {
+            let rooms = if generator == Generator::Rooms {
+                generate_rooms(&mut map)
             } else {
-                let (prev_x, prev_y) = rooms[rooms.len() - 1].center();
-                if rand::random() {
-                    create_h_tunnel(prev_x, new_x, prev_y, &mut map);
-                    create_v_tunnel(prev_y, new_y, new_x, &mut map);
-                } else {
-                    create_v_tunnel(prev_y, new_y, prev_x, &mut map);
-                    create_h_tunnel(prev_x, new_x, new_y, &mut map);
-                }
+                generate_bsp(&mut map)
+            };
+            if rooms.is_empty() {
+                return Err(MapError::NoRooms);
             }
-            rooms.push(new_room);
+            add_loops(&rooms, &mut map);
+
+            let protected = [rooms[0], rooms[rooms.len() - 1]];
+            let vault_spawns = stamp_vaults(&mut map, level, &protected, data)?;
+            connect_regions(&mut map, rooms[0].center());
+            decorate_rooms(&rooms, &mut map);
+
+            let (player_x, player_y) = rooms[0].center();
+            objects[PLAYER].set_pos(player_x, player_y);
+
+            spawn_vault_objects(&vault_spawns, &map, objects, level, data);
+            for room in &rooms {
+                place_objects(room, &map, objects, level, data);
+            }
+            // Keep guaranteed spawns out of the player's starting room when possible.
+            let others = if rooms.len() > 1 { &rooms[1..] } else { &rooms[..] };
+            place_guaranteed(|| random_spot_in_rooms(others), &map, objects, level, data);
+            map.rooms = rooms.clone();
+
+            rooms[rooms.len() - 1].center()
         }
-    }
+        Generator::Caves => {
+            let open = generate_caves(&mut map)?;
+            let vault_spawns = stamp_vaults(&mut map, level, &[], data)?;
+            decorate_caves(&open, level, &mut map);
+
+            let still_open: Vec<_> = open.iter()
+                .cloned()
+                .filter(|&(x, y)| is_safe_floor(&map[(x, y)]))
+                .collect();
+            if still_open.is_empty() {
+                return Err(MapError::TooSmall);
+            }
+            let start = random_spot_in(&still_open);
+            connect_regions(&mut map, start);
+            let open = safe_flood_fill(&map, start);
+            objects[PLAYER].set_pos(start.0, start.1);
+            spawn_vault_objects(&vault_spawns, &map, objects, level, data);
+
+            // Give the player some room to breathe before the first monsters.
+            let spawnable: Vec<_> = open.iter()
+                .cloned()
+                .filter(|&(x, y)| objects[PLAYER].distance(x, y) > CAVE_SAFE_RADIUS)
+                .collect();
+            let spawnable = if spawnable.is_empty() { open.clone() } else { spawnable };
+
+            for _ in 0..(open.len() / CAVE_TILES_PER_AREA) {
+                place_objects_at(|| random_spot_in(&spawnable), &map, objects, level, data);
+            }
+            place_guaranteed(|| random_spot_in(&spawnable), &map, objects, level, data);
+
+            // Flood fill order is by distance, so this is the farthest safe tile from the start.
+            *open.iter()
+                .rev()
+                .find(|&&(x, y)| is_safe_floor(&map[(x, y)]))
+                .unwrap_or(&start)
+        }
+    };
+
+    validate_map(&map, objects[PLAYER].pos(), (stairs_x, stairs_y))?;
+    place_traps(&mut map, objects[PLAYER].pos(), (stairs_x, stairs_y), level);
 
-    let (last_room_x, last_room_y) = rooms[rooms.len() - 1].center();
     let mut stairs = Object::new(
-        last_room_x,
-        last_room_y,
+        stairs_x,
+        stairs_y,
         '<',
         "stairs",
         colors::WHITE,
@@ -108,251 +497,610 @@ pub fn make_map(objects: &mut Vec<Object>, level: u32) -> Map {
     stairs.always_visible = true;
     objects.push(stairs);
 
-    map
+    if level > 1 {
+        let (player_x, player_y) = objects[PLAYER].pos();
+        let mut up_stairs = Object::new(
+            player_x,
+            player_y,
+            '>',
+            "up stairs",
+            colors::WHITE,
+            false,
+        );
+        up_stairs.always_visible = true;
+        objects.push(up_stairs);
+    }
+
+    Ok(map)
+}
+
+/// Hides traps on open floor, away from the player's start and the stairs.
+fn place_traps(map: &mut Map, start: (i32, i32), stairs: (i32, i32), level: u32) {
+    let candidates: Vec<_> = flood_fill(map, start)
+        .into_iter()
+        .filter(|&(x, y)| {
+            let distance = (((x - start.0).pow(2) + (y - start.1).pow(2)) as f32).sqrt();
+            map[(x, y)].kind == TileKind::Floor && (x, y) != stairs
+                && distance > TRAP_SAFE_RADIUS
+        })
+        .collect();
+    if candidates.is_empty() {
+        return;
+    }
+    for _ in 0..max_traps(level) {
+        let (x, y) = random_spot_in(&candidates);
+        map[(x, y)].trap = Some(Trap::new(TrapKind::random()));
+    }
+}
+
+/// Checks that the whole level, including the stairs, can be reached from the player
+/// without crossing lava or deep water.
+fn validate_map(map: &Map, start: (i32, i32), stairs: (i32, i32)) -> Result<(), MapError> {
+    let reached = safe_flood_fill(map, start);
+    if reached.len() < MIN_OPEN_TILES {
+        return Err(MapError::TooSmall);
+    }
+    let open_tiles = map.tiles()
+        .iter()
+        .filter(|tile| tile.kind.safely_passable())
+        .count();
+    if reached.len() != open_tiles || !reached.contains(&stairs) {
+        return Err(MapError::Unreachable);
+    }
+    Ok(())
+}
+
+/// Connects some rooms to their nearest neighbour, so the level isn't one long chain.
+fn add_loops(rooms: &[Rect], map: &mut Map) {
+    if rooms.len() < 3 {
+        return;
+    }
+    for _ in 0..(rooms.len() / LOOP_EVERY_N_ROOMS) {
+        let from = &rooms[random::rng().gen_range(0, rooms.len())];
+        let (from_x, from_y) = from.center();
+        let nearest = rooms
+            .iter()
+            .filter(|room| room.center() != from.center())
+            .min_by_key(|room| {
+                let (x, y) = room.center();
+                (x - from_x).pow(2) + (y - from_y).pow(2)
+            });
+        if let Some(nearest) = nearest {
+            connect_rooms(from, nearest, map);
+        }
+    }
+}
+
+/// Places rooms at random positions, connecting each new room to the previous one.
+fn generate_rooms(map: &mut Map) -> Vec<Rect> {
+    let mut rooms: Vec<Rect> = vec![];
+    for _ in 0..MAX_ROOMS {
+        let w = random::rng().gen_range(ROOM_MIN_SIZE, ROOM_MAX_SIZE + 1);
+        let h = random::rng().gen_range(ROOM_MIN_SIZE, ROOM_MAX_SIZE + 1);
+        if w >= map.width() || h >= map.height() {
+            continue;
+        }
+        let x = random::rng().gen_range(0, map.width() - w);
+        let y = random::rng().gen_range(0, map.height() - h);
+        let new_room = Rect::new(x, y, w, h);
+
+        let failed = rooms
+            .iter()
+            .any(|other_room| new_room.intersects_with(other_room));
+        if !failed {
+            create_room(&new_room, map);
+            if let Some(prev_room) = rooms.last() {
+                connect_rooms(prev_room, &new_room, map);
+            }
+            rooms.push(new_room);
+        }
+    }
+    rooms
+}
+
+/// Recursively splits the map in two, placing a room in each leaf and
+/// connecting the rooms of sibling partitions.
+fn generate_bsp(map: &mut Map) -> Vec<Rect> {
+    let mut rooms = vec![];
+    let whole_map = Rect::new(0, 0, map.width() - 1, map.height() - 1);
+    split_leaf(whole_map, map, &mut rooms);
+    rooms
+}
+
+/// Splits a partition until it is too small, and returns one of the rooms inside it.
+fn split_leaf(leaf: Rect, map: &mut Map, rooms: &mut Vec<Rect>) -> Rect {
+    let width = leaf.x2 - leaf.x1;
+    let height = leaf.y2 - leaf.y1;
+    let can_split_x = width >= BSP_MIN_LEAF_SIZE * 2;
+    let can_split_y = height >= BSP_MIN_LEAF_SIZE * 2;
+
+    let split_x = match (can_split_x, can_split_y) {
+        (false, false) => {
+            let w = random::rng().gen_range(ROOM_MIN_SIZE, cmp::min(ROOM_MAX_SIZE, width) + 1);
+            let h =
+                random::rng().gen_range(ROOM_MIN_SIZE, cmp::min(ROOM_MAX_SIZE, height) + 1);
+            let x = random::rng().gen_range(leaf.x1, leaf.x2 - w + 1);
+            let y = random::rng().gen_range(leaf.y1, leaf.y2 - h + 1);
+            let room = Rect::new(x, y, w, h);
+            create_room(&room, map);
+            rooms.push(room);
+            return room;
+        }
+        (true, false) => true,
+        (false, true) => false,
+        (true, true) => if width as f32 > height as f32 * 1.25 {
+            true
+        } else if height as f32 > width as f32 * 1.25 {
+            false
+        } else {
+            random::rng().gen()
+        },
+    };
+
+    let (first, second) = if split_x {
+        let split = random::rng().gen_range(BSP_MIN_LEAF_SIZE, width - BSP_MIN_LEAF_SIZE + 1);
+        (
+            Rect::new(leaf.x1, leaf.y1, split, height),
+            Rect::new(leaf.x1 + split, leaf.y1, width - split, height),
+        )
+    } else {
+        let split = random::rng().gen_range(BSP_MIN_LEAF_SIZE, height - BSP_MIN_LEAF_SIZE + 1);
+        (
+            Rect::new(leaf.x1, leaf.y1, width, split),
+            Rect::new(leaf.x1, leaf.y1 + split, width, height - split),
+        )
+    };
+
+    let first_room = split_leaf(first, map, rooms);
+    let second_room = split_leaf(second, map, rooms);
+    connect_rooms(&first_room, &second_room, map);
+    if random::rng().gen() {
+        first_room
+    } else {
+        second_room
+    }
+}
+
+/// Stamps the vaults for this level into the map, keeping clear of the protected areas.
+/// Returns the monsters and items the vaults place, to be spawned once the level is laid out.
+/// Fails if a vault guaranteed on this level could not be placed.
+fn stamp_vaults(
+    map: &mut Map,
+    level: u32,
+    protected: &[Rect],
+    data: &GameData,
+) -> Result<Vec<(i32, i32, VaultSpawn)>, MapError> {
+    let table = data.vault_table();
+    let mut vaults = table.guaranteed(level);
+    let required = vaults.len();
+    if random::rng().next_f32() < data.vault_chance {
+        if let Some(vault) = table.roll(level, &mut random::rng()) {
+            vaults.push(vault);
+        }
+    }
+
+    let mut occupied = protected.to_vec();
+    let mut spawns = vec![];
+    for (index, vault) in vaults.into_iter().enumerate() {
+        let grid = vault.random_orientation();
+        let (w, h) = (grid.len() as i32, grid[0].len() as i32);
+        let fits = w <= map.width() - 2 && h <= map.height() - 2;
+        let attempts = if fits { MAX_VAULT_ATTEMPTS } else { 0 };
+
+        let mut placed = false;
+        for _ in 0..attempts {
+            let x = random::rng().gen_range(1, map.width() - w);
+            let y = random::rng().gen_range(1, map.height() - h);
+            let footprint = Rect::new(x, y, w - 1, h - 1);
+            if occupied.iter().any(|other| footprint.intersects_with(other)) {
+                continue;
+            }
+
+            for (dx, column) in grid.iter().enumerate() {
+                for (dy, &c) in column.iter().enumerate() {
+                    let (tx, ty) = (x + dx as i32, y + dy as i32);
+                    match c {
+                        VAULT_KEEP => {}
+                        VAULT_WALL => map[(tx, ty)] = Tile::wall(),
+                        VAULT_DOOR => {
+                            map[(tx, ty)] = Tile::new(TileKind::DoorClosed)
+                        }
+                        _ => {
+                            map[(tx, ty)] = Tile::empty();
+                            if let Some(spawn) = vault.legend.get(&c) {
+                                spawns.push((tx, ty, spawn.clone()));
+                            }
+                        }
+                    }
+                }
+            }
+            occupied.push(footprint);
+            placed = true;
+            break;
+        }
+        if !placed && index < required {
+            return Err(MapError::VaultMissing);
+        }
+    }
+    Ok(spawns)
+}
+
+fn spawn_vault_objects(
+    spawns: &[(i32, i32, VaultSpawn)],
+    map: &Map,
+    objects: &mut Vec<Object>,
+    level: u32,
+    data: &GameData,
+) {
+    for &(x, y, ref spawn) in spawns {
+        if is_blocked(x, y, map, objects) {
+            continue;
+        }
+        match *spawn {
+            VaultSpawn::Monster(ref name) => if let Some(monster) = data.monster(name) {
+                objects.push(monster.spawn_outfitted(x, y, level, data));
+            },
+            VaultSpawn::Item(ref name) => if let Some(template) = data.item(name) {
+                let mut item = template.spawn(x, y);
+                roll_affixes(&mut item, level, data);
+                item.always_visible = true;
+                objects.push(item);
+            },
+        }
+    }
+}
+
+/// Digs tunnels until every safe open tile is reachable from the start, paving
+/// over any lava or deep water that cuts a region off.
+fn connect_regions(map: &mut Map, start: (i32, i32)) {
+    loop {
+        let reached = safe_flood_fill(map, start);
+        let mut is_reached = map.flags();
+        for &(x, y) in &reached {
+            is_reached[x as usize][y as usize] = true;
+        }
+
+        let mut orphan = None;
+        'search: for x in 0..map.width() {
+            for y in 0..map.height() {
+                if map[(x, y)].kind.safely_passable() && !is_reached[x as usize][y as usize] {
+                    orphan = Some((x, y));
+                    break 'search;
+                }
+            }
+        }
+        let (orphan_x, orphan_y) = match orphan {
+            Some(orphan) => orphan,
+            None => return,
+        };
+
+        let distance = |&&(x, y): &&(i32, i32)| (x - orphan_x).pow(2) + (y - orphan_y).pow(2);
+        let &nearest = reached.iter().min_by_key(distance).unwrap();
+        dig_tunnel((orphan_x, orphan_y), nearest, map);
+    }
+}
+
+pub fn is_safe_floor(tile: &Tile) -> bool {
+    !tile.blocked() && !tile.kind.is_hazard()
+}
+
+/// Puts doors in the openings of room walls and scatters rubble on room floors.
+fn decorate_rooms(rooms: &[Rect], map: &mut Map) {
+    for room in rooms {
+        for x in room.x1..(room.x2 + 1) {
+            for y in room.y1..(room.y2 + 1) {
+                let on_wall = x == room.x1 || x == room.x2 || y == room.y1 || y == room.y2;
+                if map[(x, y)].kind != TileKind::Floor {
+                    continue;
+                }
+                if on_wall && is_doorway(x, y, map) {
+                    if random::rng().next_f32() < DOOR_CHANCE {
+                        map[(x, y)] = Tile::new(TileKind::DoorClosed);
+                    }
+                } else if !on_wall && random::rng().next_f32() < RUBBLE_CHANCE {
+                    map[(x, y)] = Tile::new(TileKind::Rubble);
+                }
+            }
+        }
+    }
+}
+
+/// A floor tile with walls on two opposite sides, like a tunnel entering a room.
+fn is_doorway(x: i32, y: i32, map: &Map) -> bool {
+    if !map.in_bounds(x, y) || map.on_border(x, y) {
+        return false;
+    }
+    let wall = |x: i32, y: i32| map[(x, y)].kind == TileKind::Wall;
+    (wall(x - 1, y) && wall(x + 1, y)) || (wall(x, y - 1) && wall(x, y + 1))
+}
+
+/// Adds pools of water or lava and scattered trees to a cave level.
+fn decorate_caves(open: &[(i32, i32)], level: u32, map: &mut Map) {
+    for _ in 0..CAVE_POOLS {
+        let (center_x, center_y) = random_spot_in(open);
+        let radius = random::rng().gen_range(1, CAVE_POOL_MAX_RADIUS + 1);
+        let lava = level >= LAVA_MIN_LEVEL && random::rng().next_f32() < LAVA_POOL_CHANCE;
+        for &(x, y) in open {
+            let distance = (((x - center_x).pow(2) + (y - center_y).pow(2)) as f32).sqrt();
+            let tile = &mut map[(x, y)];
+            if distance > radius as f32 || tile.kind != TileKind::Floor {
+                continue;
+            }
+            tile.kind = if lava {
+                TileKind::Lava
+            } else if distance < (radius - 1) as f32 {
+                TileKind::DeepWater
+            } else {
+                TileKind::ShallowWater
+            };
+        }
+    }
+
+    for &(x, y) in open {
+        let tile = &mut map[(x, y)];
+        if tile.kind == TileKind::Floor && random::rng().next_f32() < TREE_CHANCE {
+            tile.kind = TileKind::Tree;
+        }
+    }
+}
+
+/// Grows caves with cellular automata and fills in every pocket that isn't
+/// part of the largest open region. Returns the open tiles that remain.
+fn generate_caves(map: &mut Map) -> Result<Vec<(i32, i32)>, MapError> {
+    for x in 0..map.width() {
+        for y in 0..map.height() {
+            let wall = map.on_border(x, y) || random::rng().next_f32() < CAVE_WALL_CHANCE;
+            map[(x, y)] = if wall { Tile::wall() } else { Tile::empty() };
+        }
+    }
+
+    for _ in 0..CAVE_SMOOTHING_STEPS {
+        smooth_caves(map);
+    }
+
+    let mut largest: Vec<(i32, i32)> = vec![];
+    let mut visited = map.flags();
+    for x in 0..map.width() {
+        for y in 0..map.height() {
+            if visited[x as usize][y as usize] || map[(x, y)].blocked() {
+                continue;
+            }
+            let region = flood_fill(map, (x, y));
+            for &(rx, ry) in &region {
+                visited[rx as usize][ry as usize] = true;
+            }
+            if region.len() > largest.len() {
+                largest = region;
+            }
+        }
+    }
+
+    if largest.len() < CAVE_MIN_OPEN_TILES {
+        return Err(MapError::TooSmall);
+    }
+    let mut keep = map.flags();
+    for &(x, y) in &largest {
+        keep[x as usize][y as usize] = true;
+    }
+    for x in 0..map.width() {
+        for y in 0..map.height() {
+            if !keep[x as usize][y as usize] {
+                map[(x, y)] = Tile::wall();
+            }
+        }
+    }
+    Ok(largest)
+}
+
+/// One cellular automata step: tiles surrounded by walls become walls, others open up.
+fn smooth_caves(map: &mut Map) {
+    let previous = map.clone();
+    for x in 1..(map.width() - 1) {
+        for y in 1..(map.height() - 1) {
+            let mut walls = 0;
+            for dx in -1..2 {
+                for dy in -1..2 {
+                    if (dx, dy) != (0, 0) && previous[(x + dx, y + dy)].blocked() {
+                        walls += 1;
+                    }
+                }
+            }
+            map[(x, y)] = if walls >= 5 {
+                Tile::wall()
+            } else {
+                Tile::empty()
+            };
+        }
+    }
+}
+
+/// Returns every open tile reachable from the start, ordered by walking distance.
+pub fn flood_fill(map: &Map, start: (i32, i32)) -> Vec<(i32, i32)> {
+    flood_fill_through(map, start, TileKind::passable)
+}
+
+/// Like `flood_fill`, but treats lava and deep water as walls.
+pub fn safe_flood_fill(map: &Map, start: (i32, i32)) -> Vec<(i32, i32)> {
+    flood_fill_through(map, start, TileKind::safely_passable)
+}
+
+fn flood_fill_through<F>(map: &Map, start: (i32, i32), walkable: F) -> Vec<(i32, i32)>
+where
+    F: Fn(TileKind) -> bool,
+{
+    let mut visited = map.flags();
+    let mut queue = VecDeque::new();
+    let mut reached = vec![];
+    visited[start.0 as usize][start.1 as usize] = true;
+    queue.push_back(start);
+
+    while let Some((x, y)) = queue.pop_front() {
+        reached.push((x, y));
+        for &(dx, dy) in &[(1, 0), (-1, 0), (0, 1), (0, -1)] {
+            let (nx, ny) = (x + dx, y + dy);
+            if !map.in_bounds(nx, ny) {
+                continue;
+            }
+            if !visited[nx as usize][ny as usize] && walkable(map[(nx, ny)].kind) {
+                visited[nx as usize][ny as usize] = true;
+                queue.push_back((nx, ny));
+            }
+        }
+    }
+    reached
+}
+
+/// Digs an L-shaped tunnel between the centers of two rooms.
+fn connect_rooms(from: &Rect, to: &Rect, map: &mut Map) {
+    dig_tunnel(from.center(), to.center(), map);
+}
+
+/// Digs an L-shaped tunnel between two points, bending at a random corner.
+fn dig_tunnel((prev_x, prev_y): (i32, i32), (new_x, new_y): (i32, i32), map: &mut Map) {
+    if random::rng().gen() {
+        create_h_tunnel(prev_x, new_x, prev_y, map);
+        create_v_tunnel(prev_y, new_y, new_x, map);
+    } else {
+        create_v_tunnel(prev_y, new_y, prev_x, map);
+        create_h_tunnel(prev_x, new_x, new_y, map);
+    }
 }
 
 fn create_room(room: &Rect, map: &mut Map) {
     for x in (room.x1 + 1)..room.x2 {
         for y in (room.y1 + 1)..room.y2 {
-            map[x as usize][y as usize] = Tile::empty();
+            map[(x, y)] = Tile::empty();
         }
     }
 }
 
 fn create_h_tunnel(x1: i32, x2: i32, y: i32, map: &mut Map) {
     for x in cmp::min(x1, x2)..(cmp::max(x1, x2) + 1) {
-        map[x as usize][y as usize] = Tile::empty();
+        map[(x, y)] = Tile::empty();
     }
 }
 
 fn create_v_tunnel(y1: i32, y2: i32, x: i32, map: &mut Map) {
     for y in cmp::min(y1, y2)..(cmp::max(y1, y2) + 1) {
-        map[x as usize][y as usize] = Tile::empty();
+        map[(x, y)] = Tile::empty();
     }
 }
 
-pub fn place_objects(room: &Rect, map: &Map, objects: &mut Vec<Object>, level: u32) {
-    let max_monsters = from_dungeon_level(
-        &[
-            Transition { level: 1, value: 2 },
-            Transition { level: 4, value: 3 },
-            Transition { level: 6, value: 5 },
-        ],
-        level,
-    );
-    let num_monsters = rand::thread_rng().gen_range(0, max_monsters + 1);
+pub fn place_objects(
+    room: &Rect,
+    map: &Map,
+    objects: &mut Vec<Object>,
+    level: u32,
+    data: &GameData,
+) {
+    place_objects_at(|| random_spot_in_rooms(&[*room]), map, objects, level, data);
+}
 
-    let troll_chance = from_dungeon_level(
-        &[
-            Transition {
-                level: 3,
-                value: 15,
-            },
-            Transition {
-                level: 5,
-                value: 30,
-            },
-            Transition {
-                level: 7,
-                value: 60,
-            },
-        ],
-        level,
-    );
-    let monster_chances = &mut [
-        Weighted {
-            weight: 80,
-            item: "orc",
-        },
-        Weighted {
-            weight: troll_chance,
-            item: "troll",
-        },
-    ];
+/// Places the monsters and items of a single area, using `random_spot` to pick positions in it.
+pub fn place_objects_at<F>(
+    mut random_spot: F,
+    map: &Map,
+    objects: &mut Vec<Object>,
+    level: u32,
+    data: &GameData,
+) where
+    F: FnMut() -> (i32, i32),
+{
+    let monster_table = data.monster_table();
+    let num_monsters = random::rng().gen_range(0, max_monsters(level) + 1);
 
     for _ in 0..num_monsters {
-        let x = rand::thread_rng().gen_range(room.x1 + 1, room.x2);
-        let y = rand::thread_rng().gen_range(room.y1 + 1, room.y2);
+        let (x, y) = random_spot();
 
         if !is_blocked(x, y, map, objects) {
-            let monster_choice = WeightedChoice::new(monster_chances);
-            let mut monster = match monster_choice.ind_sample(&mut rand::thread_rng()) {
-                "orc" => {
-                    let mut orc = Object::new(x, y, 'o', "orc", colors::DESATURATED_GREEN, true);
-                    orc.fighter = Some(Fighter {
-                        hp: 20,
-                        base_max_hp: 20,
-                        base_defense: 0,
-                        base_power: 4,
-                        on_death: DeathCallback::Monster,
-                        xp: 35,
-                    });
-                    orc.ai = Some(Ai::Basic);
-                    orc
-                }
-                "troll" => {
-                    let mut troll = Object::new(x, y, 'T', "troll", colors::DARKER_GREEN, true);
-                    troll.fighter = Some(Fighter {
-                        hp: 16,
-                        base_max_hp: 30,
-                        base_defense: 2,
-                        base_power: 8,
-                        on_death: DeathCallback::Monster,
-                        xp: 100,
-                    });
-                    troll.ai = Some(Ai::Basic);
-                    troll
-                }
-                _ => unreachable!(),
-            };
-
-            monster.alive = true;
-            objects.push(monster);
+            if let Some(monster) = monster_table.roll(level, &mut random::rng()) {
+                objects.push(monster.spawn_outfitted(x, y, level, data));
+            }
         }
     }
 
-    let max_items = from_dungeon_level(
-        &[
-            Transition { level: 1, value: 1 },
-            Transition { level: 4, value: 2 },
-        ],
-        level,
-    );
-
-    let item_chances = &mut [
-        Weighted {
-            weight: 35,
-            item: Item::Heal,
-        },
-        Weighted {
-            weight: from_dungeon_level(
-                &[
-                    Transition {
-                        level: 4,
-                        value: 25,
-                    },
-                ],
-                level,
-            ),
-            item: Item::Lightning,
-        },
-        Weighted {
-            weight: from_dungeon_level(
-                &[
-                    Transition {
-                        level: 6,
-                        value: 25,
-                    },
-                ],
-                level,
-            ),
-            item: Item::Fireball,
-        },
-        Weighted {
-            weight: from_dungeon_level(
-                &[
-                    Transition {
-                        level: 2,
-                        value: 10,
-                    },
-                ],
-                level,
-            ),
-            item: Item::Confuse,
-        },
-        Weighted {
-            weight: from_dungeon_level(&[Transition { level: 4, value: 5 }], level),
-            item: Item::Sword,
-        },
-        Weighted {
-            weight: from_dungeon_level(
-                &[
-                    Transition {
-                        level: 8,
-                        value: 15,
-                    },
-                ],
-                level,
-            ),
-            item: Item::Shield,
-        },
-    ];
-    let num_items = rand::thread_rng().gen_range(0, max_items + 1);
+    let item_table = data.item_table();
+    let num_items = random::rng().gen_range(0, max_items(level) + 1);
 
     for _ in 0..num_items {
-        let x = rand::thread_rng().gen_range(room.x1 + 1, room.x2);
-        let y = rand::thread_rng().gen_range(room.y1 + 1, room.y2);
+        let (x, y) = random_spot();
 
         if !is_blocked(x, y, map, objects) {
-            let item_choice = WeightedChoice::new(item_chances);
-            let mut item = match item_choice.ind_sample(&mut rand::thread_rng()) {
-                Item::Heal => {
-                    let mut object =
-                        Object::new(x, y, '!', "healing potion", colors::VIOLET, false);
-                    object.item = Some(Item::Heal);
-                    object
-                }
-                Item::Lightning => {
-                    let mut object = Object::new(
-                        x,
-                        y,
-                        '#',
-                        "scroll of lightning bolt",
-                        colors::LIGHT_YELLOW,
-                        false,
-                    );
-                    object.item = Some(Item::Lightning);
-                    object
-                }
-                Item::Fireball => {
-                    let mut object =
-                        Object::new(x, y, '#', "scroll of fireball", colors::LIGHT_YELLOW, false);
-                    object.item = Some(Item::Fireball);
-                    object
-                }
-                Item::Confuse => {
-                    let mut object = Object::new(
-                        x,
-                        y,
-                        '#',
-                        "scroll of confusion",
-                        colors::LIGHT_YELLOW,
-                        false,
-                    );
-                    object.item = Some(Item::Confuse);
-                    object
-                }
-                Item::Sword => {
-                    let mut object = Object::new(x, y, '/', "sword", colors::SKY, false);
-                    object.item = Some(Item::Sword);
-                    object.equipment = Some(Equipment {
-                        equipped: false,
-                        slot: Slot::RightHand,
-                        power_bonus: 3,
-                        defense_bonus: 0,
-                        max_hp_bonus: 0,
-                    });
-                    object
-                }
-                Item::Shield => {
-                    let mut object = Object::new(x, y, '[', "shield", colors::DARKER_ORANGE, false);
-                    object.item = Some(Item::Shield);
-                    object.equipment = Some(Equipment {
-                        equipped: false,
-                        slot: Slot::LeftHand,
-                        power_bonus: 0,
-                        defense_bonus: 1,
-                        max_hp_bonus: 0,
-                    });
-                    object
-                }
-            };
+            if let Some(template) = item_table.roll(level, &mut random::rng()) {
+                let mut item = template.spawn(x, y);
+                roll_affixes(&mut item, level, data);
+                item.always_visible = true;
+                objects.push(item);
+            }
+        }
+    }
+}
+
+/// Places one of each monster and item that is guaranteed on this level.
+fn place_guaranteed<F>(
+    mut random_spot: F,
+    map: &Map,
+    objects: &mut Vec<Object>,
+    level: u32,
+    data: &GameData,
+) where
+    F: FnMut() -> (i32, i32),
+{
+    for monster in data.monster_table().guaranteed(level) {
+        if let Some((x, y)) = random_free_spot(&mut random_spot, map, objects) {
+            objects.push(monster.spawn_outfitted(x, y, level, data));
+        }
+    }
+    for template in data.item_table().guaranteed(level) {
+        if let Some((x, y)) = random_free_spot(&mut random_spot, map, objects) {
+            let mut item = template.spawn(x, y);
             item.always_visible = true;
             objects.push(item);
         }
     }
 }
 
+fn random_free_spot<F>(random_spot: &mut F, map: &Map, objects: &[Object]) -> Option<(i32, i32)>
+where
+    F: FnMut() -> (i32, i32),
+{
+    for _ in 0..MAX_PLACEMENT_ATTEMPTS {
+        let (x, y) = random_spot();
+        if !is_blocked(x, y, map, objects) {
+            return Some((x, y));
+        }
+    }
+    None
+}
+
+/// A random position inside one of the rooms, excluding their walls.
+fn random_spot_in_rooms(rooms: &[Rect]) -> (i32, i32) {
+    let room = &rooms[random::rng().gen_range(0, rooms.len())];
+    let x = random::rng().gen_range(room.x1 + 1, room.x2);
+    let y = random::rng().gen_range(room.y1 + 1, room.y2);
+    (x, y)
+}
+
+fn random_spot_in(tiles: &[(i32, i32)]) -> (i32, i32) {
+    tiles[random::rng().gen_range(0, tiles.len())]
+}
+
+/// Maximum number of monsters placed in a single room.
+pub fn max_monsters(level: u32) -> u32 {
+    value_at(MAX_MONSTERS_PER_ROOM, Interpolation::Step, level)
+}
+
+/// Number of traps hidden on a level.
+pub fn max_traps(level: u32) -> u32 {
+    value_at(MAX_TRAPS, Interpolation::Step, level)
+}
+
+/// Maximum number of items placed in a single room.
+pub fn max_items(level: u32) -> u32 {
+    value_at(MAX_ITEMS_PER_ROOM, Interpolation::Step, level)
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct Rect {
     pub x1: i32,
     pub x2: i32,
@@ -381,11 +1129,3 @@ impl Rect {
             && (self.y2 >= other.y1)
     }
 }
-
-fn from_dungeon_level(table: &[Transition], level: u32) -> u32 {
-    table
-        .iter()
-        .rev()
-        .find(|transition| level >= transition.level)
-        .map_or(0, |transition| transition.value)
-}
@@ -0,0 +1,130 @@
+use data::{validate_color, GameData};
+use spawn::{value_at, Interpolation, SpawnRule, Spawnable, Transition};
+use item::equip_if_free;
+use affix::roll_affixes;
+use object::*;
+
+use rand::Rng;
+use random;
+
+/// A kind of monster as defined in the data files.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MonsterTemplate {
+    pub name: String,
+    pub glyph: char,
+    pub color: [f32; 4],
+    /// Maximum hit points.
+    pub hp: i32,
+    /// Hit points the monster is placed with, if it starts out wounded.
+    #[serde(default)]
+    pub starting_hp: Option<i32>,
+    pub defense: i32,
+    pub power: i32,
+    pub xp: i32,
+    pub ai: Ai,
+    #[serde(default)]
+    pub resistances: Resistances,
+    pub spawn: SpawnRule,
+    /// Items the monster may be carrying. Equipment is worn if there is room for it.
+    #[serde(default)]
+    pub loot: Vec<Loot>,
+}
+
+/// An item a monster may carry, rolled when the monster is placed.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Loot {
+    pub item: String,
+    /// Chance in percent of carrying the item, by dungeon level.
+    pub chance: Vec<Transition>,
+}
+
+impl MonsterTemplate {
+    /// Creates a living monster from this template at the given position.
+    pub fn spawn(&self, x: i32, y: i32) -> Object {
+        let mut monster = Object::new(x, y, self.glyph, &self.name, self.color, true);
+        monster.fighter = Some(Fighter {
+            hp: self.starting_hp.unwrap_or(self.hp),
+            base_max_hp: self.hp,
+            base_defense: self.defense,
+            base_power: self.power,
+            on_death: DeathCallback::Monster,
+            xp: self.xp,
+            resistances: self.resistances,
+        });
+        monster.ai = Some(self.ai.clone());
+        monster.alive = true;
+        monster
+    }
+
+    /// Creates a monster carrying loot rolled for the given dungeon level.
+    pub fn spawn_outfitted(&self, x: i32, y: i32, level: u32, data: &GameData) -> Object {
+        let mut monster = self.spawn(x, y);
+        for loot in &self.loot {
+            let chance = value_at(&loot.chance, Interpolation::Step, level);
+            if random::rng().gen_range(0, 100) >= chance {
+                continue;
+            }
+            if let Some(template) = data.item(&loot.item) {
+                let mut item = template.spawn(x, y);
+                roll_affixes(&mut item, level, data);
+                monster.inventory.push(item);
+                let index = monster.inventory.len() - 1;
+                equip_if_free(index, &mut monster.inventory);
+            }
+        }
+        monster
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("name must not be empty".into());
+        }
+        if self.glyph.is_whitespace() {
+            return Err("glyph must be a visible character".into());
+        }
+        validate_color(self.color)?;
+        if self.hp <= 0 {
+            return Err(format!("hp must be positive, got {}", self.hp));
+        }
+        match self.starting_hp {
+            Some(hp) if hp <= 0 || hp > self.hp => {
+                return Err(format!(
+                    "starting_hp must be between 1 and hp ({}), got {}",
+                    self.hp, hp
+                ))
+            }
+            _ => {}
+        }
+        if self.xp < 0 {
+            return Err(format!("xp must not be negative, got {}", self.xp));
+        }
+        if self.ai != Ai::Basic {
+            return Err(format!("unsupported ai {:?}", self.ai));
+        }
+        for loot in &self.loot {
+            if loot.chance.windows(2).any(|w| w[0].level >= w[1].level) {
+                return Err(format!(
+                    "loot '{}': chance levels must be strictly increasing",
+                    loot.item
+                ));
+            }
+            if loot.chance.iter().any(|transition| transition.value > 100) {
+                return Err(format!(
+                    "loot '{}': chance must be at most 100 percent",
+                    loot.item
+                ));
+            }
+        }
+        self.spawn.validate()
+    }
+}
+
+impl Spawnable for MonsterTemplate {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn spawn_rule(&self) -> &SpawnRule {
+        &self.spawn
+    }
+}
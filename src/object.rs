@@ -1,13 +1,20 @@
 use map::*;
 use map::Map;
-use rand::{self, Rng};
+use rand::Rng;
+use random;
 
 use std::cmp;
 
-use item::{Equipment, Item};
+use item::{take_from_inventory, wear_armour, wear_weapons, Charges, Equipment, Item, Missile};
+use event::{EventBus, GameEvent};
+use trap::{perception_check, trigger_trap};
+use camera::Camera;
 use ::*;
 
-#[derive(Debug/*, Serialize, Deserialize*/)]
+/// Chance per step in deep water that an unequipped item slips away.
+const DEEP_WATER_DROP_CHANCE: f32 = 0.2;
+
+#[derive(Clone, Debug/*, Serialize, Deserialize*/)]
 pub struct Object {
     pub x: i32,
     pub y: i32,
@@ -20,7 +27,13 @@ pub struct Object {
     pub ai: Option<Ai>,
     pub item: Option<Item>,
     pub equipment: Option<Equipment>,
+    /// Number of items in a stack, or `None` for items that don't stack.
+    pub quantity: Option<u32>,
+    pub charges: Option<Charges>,
+    pub missile: Option<Missile>,
     pub always_visible: bool,
+    /// Items carried by anything but the player, whose items are in `Game::inventory`.
+    pub inventory: Vec<Object>,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
@@ -38,6 +51,28 @@ pub struct Fighter {
     pub base_power: i32,
     pub on_death: DeathCallback,
     pub xp: i32,
+    pub resistances: Resistances,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum DamageType {
+    Physical,
+    Fire,
+    Lightning,
+    Poison,
+    Cold,
+}
+
+/// Damage reduction in percent per damage type.
+/// Negative values are vulnerabilities and increase the damage taken.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Resistances {
+    pub physical: i32,
+    pub fire: i32,
+    pub lightning: i32,
+    pub poison: i32,
+    pub cold: i32,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
@@ -53,6 +88,51 @@ pub enum Ai {
         previous_ai: Box<Ai>,
         num_turns: i32,
     },
+    /// Heading towards a noise until the player is spotted or it gives up.
+    Alerted {
+        x: i32,
+        y: i32,
+        previous_ai: Box<Ai>,
+        num_turns: i32,
+    },
+}
+
+impl std::fmt::Display for DamageType {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            DamageType::Physical => write!(f, "physical"),
+            DamageType::Fire => write!(f, "fire"),
+            DamageType::Lightning => write!(f, "lightning"),
+            DamageType::Poison => write!(f, "poison"),
+            DamageType::Cold => write!(f, "cold"),
+        }
+    }
+}
+
+impl Resistances {
+    pub fn get(&self, damage_type: DamageType) -> i32 {
+        match damage_type {
+            DamageType::Physical => self.physical,
+            DamageType::Fire => self.fire,
+            DamageType::Lightning => self.lightning,
+            DamageType::Poison => self.poison,
+            DamageType::Cold => self.cold,
+        }
+    }
+}
+
+impl std::ops::Add for Resistances {
+    type Output = Resistances;
+
+    fn add(self, other: Resistances) -> Resistances {
+        Resistances {
+            physical: self.physical + other.physical,
+            fire: self.fire + other.fire,
+            lightning: self.lightning + other.lightning,
+            poison: self.poison + other.poison,
+            cold: self.cold + other.cold,
+        }
+    }
 }
 
 impl Object {
@@ -70,16 +150,25 @@ impl Object {
             item: None,
             always_visible: false,
             equipment: None,
+            quantity: None,
+            charges: None,
+            missile: None,
+            inventory: vec![],
         }
     }
 
-    pub fn draw(&self, con: &mut Console) {
-        con.set_default_foreground(self.color);
-        con.put_char(self.x, self.y, self.char, BackgroundFlag::None);
+    /// Draws the object in a colour that can differ from its own, as for unidentified items.
+    pub fn draw(&self, con: &mut Console, camera: &Camera, color: [f32; 4]) {
+        if let Some((x, y)) = camera.to_screen(self.x, self.y) {
+            con.set_default_foreground(color);
+            con.put_char(x, y, self.char, BackgroundFlag::None);
+        }
     }
 
-    pub fn clear(&self, con: &mut Console) {
-        con.put_char(self.x, self.y, ' ', BackgroundFlag::None);
+    pub fn clear(&self, con: &mut Console, camera: &Camera) {
+        if let Some((x, y)) = camera.to_screen(self.x, self.y) {
+            con.put_char(x, y, ' ', BackgroundFlag::None);
+        }
     }
 
     pub fn pos(&self) -> (i32, i32) {
@@ -97,7 +186,7 @@ impl Object {
         ((dx.pow(2) + dy.pow(2)) as f32).sqrt()
     }
 
-    pub fn take_damage(&mut self, damage: i32, messages: &mut Messages) -> Option<i32> {
+    pub fn take_damage(&mut self, damage: i32, events: &mut EventBus) -> Option<i32> {
         if let Some(fighter) = self.fighter.as_mut() {
             if damage > 0 {
                 fighter.hp -= damage;
@@ -106,7 +195,7 @@ impl Object {
         if let Some(fighter) = self.fighter {
             if fighter.hp <= 0 {
                 self.alive = false;
-                fighter.on_death.callback(self, messages);
+                fighter.on_death.callback(self, events);
                 return Some(fighter.xp);
             }
         }
@@ -114,29 +203,64 @@ impl Object {
     }
 
     pub fn attack(&mut self, target: &mut Object, game: &mut Game) {
-        let damage = self.power(game) - target.defense(game);
+        let damage = target.resisted_damage(
+            self.power(game) - target.defense(game),
+            DamageType::Physical,
+            game,
+        );
         if damage > 0 {
-            game.log.add(
-                format!(
-                    "{} attacks {} for {} hit points.",
-                    self.name, target.name, damage
-                ),
-                colors::RED,
-            );
-            if let Some(xp) = target.take_damage(damage, &mut game.log) {
-                self.fighter.as_mut().unwrap().xp += xp;
+            game.events.emit(GameEvent::Attacked {
+                attacker: self.name.clone(),
+                target: target.name.clone(),
+                damage: damage,
+                target_is_player: target.is_player(),
+            });
+            if let Some(xp) = target.take_damage(damage, &mut game.events) {
+                if self.is_player() {
+                    game.events.emit(GameEvent::XpGained { amount: xp });
+                }
             };
+            self.on_hit_effects(target, game);
+            if target.is_player() {
+                wear_armour(target, game);
+            }
         } else {
-            game.log.add(
-                format!(
-                    "{} attacks {} but it has no effect!",
-                    self.name, target.name
-                ),
-                colors::WHITE,
-            );
+            game.events.emit(GameEvent::Missed {
+                attacker: self.name.clone(),
+                target: target.name.clone(),
+            });
+        }
+        if self.is_player() {
+            wear_weapons(self, game);
+        }
+    }
+
+    /// Extra damage from magic weapons after an attack lands.
+    fn on_hit_effects(&self, target: &mut Object, game: &mut Game) {
+        for on_hit in self.get_all_equipped(game).iter().filter_map(|e| e.on_hit) {
+            if !target.alive {
+                return;
+            }
+            let damage = target.resisted_damage(on_hit.damage, on_hit.damage_type, game);
+            game.events.emit(GameEvent::Damaged {
+                target: target.name.clone(),
+                damage: damage,
+                damage_type: on_hit.damage_type,
+                target_is_player: target.is_player(),
+            });
+            if let Some(xp) = target.take_damage(damage, &mut game.events) {
+                if self.is_player() {
+                    game.events.emit(GameEvent::XpGained { amount: xp });
+                }
+            }
         }
     }
 
+    pub fn is_player(&self) -> bool {
+        self.fighter
+            .map_or(false, |f| f.on_death == DeathCallback::Player)
+    }
+
     pub fn heal(&mut self, amount: i32, game: &Game) {
         let max_hp = self.max_hp(game);
         if let Some(ref mut fighter) = self.fighter {
@@ -147,6 +271,11 @@ impl Object {
         }
     }
 
+    /// Whether this item can be merged into the other's stack.
+    pub fn stacks_with(&self, other: &Object) -> bool {
+        self.quantity.is_some() && other.quantity.is_some() && self.name == other.name
+    }
+
     pub fn distance(&self, x: i32, y: i32) -> f32 {
         (((x - self.x).pow(2) + (y - self.y).pow(2)) as f32).sqrt()
     }
@@ -163,7 +292,7 @@ impl Object {
             if !equipment.equipped {
                 equipment.equipped = true;
                 log.add(
-                    format!("Equipped {} on {}.", self.name, equipment.slot),
+                    format!("Equipped {} on {}.", self.name, equipment.worn_on()),
                     colors::LIGHT_GREEN,
                 );
             }
@@ -187,7 +316,7 @@ impl Object {
             if equipment.equipped {
                 equipment.equipped = false;
                 log.add(
-                    format!("Dequipped {} from {}.", self.name, equipment.slot),
+                    format!("Dequipped {} from {}.", self.name, equipment.worn_on()),
                     colors::LIGHT_YELLOW,
                 );
             }
@@ -203,29 +332,46 @@ impl Object {
         let base_power = self.fighter.map_or(0, |f| f.base_power);
         let bonus = self.get_all_equipped(game)
             .iter()
-            .fold(0, |sum, e| sum + e.power_bonus);
+            .fold(0, |sum, e| sum + e.power());
         base_power + bonus
     }
 
+    /// Total resistance against a damage type, capped at full immunity.
+    pub fn resistance(&self, damage_type: DamageType, game: &Game) -> i32 {
+        let base = self.fighter.map_or(Resistances::default(), |f| f.resistances);
+        let total = self.get_all_equipped(game)
+            .iter()
+            .fold(base, |sum, e| sum + e.resistances);
+        cmp::min(total.get(damage_type), 100)
+    }
+
+    /// Returns how much of the given damage remains after resistances are applied.
+    pub fn resisted_damage(&self, damage: i32, damage_type: DamageType, game: &Game) -> i32 {
+        if damage <= 0 {
+            return 0;
+        }
+        damage * (100 - self.resistance(damage_type, game)) / 100
+    }
+
     pub fn defense(&self, game: &Game) -> i32 {
         let base_defense = self.fighter.map_or(0, |f| f.base_defense);
         let bonus = self.get_all_equipped(game)
             .iter()
-            .fold(0, |sum, e| sum + e.defense_bonus);
+            .fold(0, |sum, e| sum + e.defense());
         base_defense + bonus
     }
 
     fn get_all_equipped(&self, game: &Game) -> Vec<Equipment> {
-        // Hacky...
-        if self.name == "player" {
-            game.inventory
-                .iter()
-                .filter(|item| item.equipment.map_or(false, |e| e.equipped))
-                .map(|item| item.equipment.unwrap())
-                .collect()
+        let inventory = if self.is_player() {
+            &game.inventory
         } else {
-            vec![]
-        }
+            &self.inventory
+        };
+        inventory
+            .iter()
+            .filter_map(|item| item.equipment)
+            .filter(|equipment| equipment.equipped)
+            .collect()
     }
 
     pub fn max_hp(&self, game: &Game) -> i32 {
@@ -238,24 +384,115 @@ impl Object {
 }
 
 impl DeathCallback {
-    pub fn callback(self, object: &mut Object, messages: &mut Messages) {
-        let callback: fn(&mut Object, &mut Messages) = match self {
+    pub fn callback(self, object: &mut Object, events: &mut EventBus) {
+        let callback: fn(&mut Object, &mut EventBus) = match self {
             DeathCallback::Player => player_death,
             DeathCallback::Monster => monster_death,
         };
-        callback(object, messages);
+        callback(object, events);
     }
 }
 
-pub fn move_by(id: usize, dx: i32, dy: i32, map: &Map, objects: &mut [Object]) {
+/// Moves an object if the way is free, or opens a closed door in the way instead.
+/// Returns whether the object moved.
+pub fn move_by(id: usize, dx: i32, dy: i32, map: &mut Map, objects: &mut [Object]) -> bool {
     let (x, y) = objects[id].pos();
+    match map.get_mut(x + dx, y + dy) {
+        Some(tile) if tile.kind == TileKind::DoorClosed => {
+            tile.kind = TileKind::DoorOpen;
+            return false;
+        }
+        Some(_) => {}
+        None => return false,
+    }
     if !is_blocked(x + dx, y + dy, map, objects) {
         objects[id].x += dx;
         objects[id].y += dy;
+        return true;
+    }
+    false
+}
+
+/// Applies the effects of the tile an object just walked onto.
+pub fn enter_tile(id: usize, objects: &mut [Object], game: &mut Game, fov: &Fov) {
+    let (x, y) = objects[id].pos();
+    let kind = game.map[(x, y)].kind;
+
+    if let Some((damage, damage_type)) = kind.damage_on_entry() {
+        hurt(id, damage, damage_type, objects, game);
+    }
+    trigger_trap(id, objects, game, fov);
+    if id == PLAYER {
+        perception_check(objects, game);
+    }
+}
+
+/// Swimming through deep water may cost the player an unequipped item. It washes
+/// up on a neighbouring shore tile, or sinks if there is none.
+fn lose_item_in_water(objects: &mut Vec<Object>, game: &mut Game) {
+    let (x, y) = objects[PLAYER].pos();
+    if game.map[(x, y)].kind != TileKind::DeepWater
+        || random::rng().next_f32() >= DEEP_WATER_DROP_CHANCE
+    {
+        return;
+    }
+    let loose: Vec<usize> = game.inventory
+        .iter()
+        .enumerate()
+        .filter(|&(_, item)| !item.equipment.map_or(false, |e| e.equipped))
+        .map(|(index, _)| index)
+        .collect();
+    if loose.is_empty() {
+        return;
+    }
+    let index = loose[random::rng().gen_range(0, loose.len())];
+    let mut item = take_from_inventory(&mut game.inventory, index, 1);
+
+    let mut shore = vec![];
+    for dx in -1..2 {
+        for dy in -1..2 {
+            let (shore_x, shore_y) = (x + dx, y + dy);
+            if game.map.in_bounds(shore_x, shore_y)
+                && is_safe_floor(&game.map[(shore_x, shore_y)])
+            {
+                shore.push((shore_x, shore_y));
+            }
+        }
+    }
+    game.events.emit(GameEvent::ItemSwept {
+        name: game.identification.name_of(&item),
+        washed_ashore: !shore.is_empty(),
+    });
+    if !shore.is_empty() {
+        let (shore_x, shore_y) = shore[random::rng().gen_range(0, shore.len())];
+        item.set_pos(shore_x, shore_y);
+        objects.push(item);
+    }
+}
+
+/// Deals damage from the surroundings, such as lava or traps, after resistances.
+pub fn hurt(id: usize, damage: i32, damage_type: DamageType, objects: &mut [Object], game: &mut Game) {
+    if objects[id].fighter.is_none() {
+        return;
     }
+    let damage = objects[id].resisted_damage(damage, damage_type, game);
+    game.events.emit(GameEvent::Damaged {
+        target: objects[id].name.clone(),
+        damage: damage,
+        damage_type: damage_type,
+        target_is_player: id == PLAYER,
+    });
+    objects[id].take_damage(damage, &mut game.events);
 }
 
-pub fn player_move_or_attack(id: usize, dx: i32, dy: i32, objects: &mut [Object], game: &mut Game) {
+pub fn player_move_or_attack(
+    id: usize,
+    dx: i32,
+    dy: i32,
+    objects: &mut Vec<Object>,
+    game: &mut Game,
+    fov: &Fov,
+) {
     let x = objects[PLAYER].x + dx;
     let y = objects[PLAYER].y + dy;
 
@@ -269,19 +506,28 @@ pub fn player_move_or_attack(id: usize, dx: i32, dy: i32, objects: &mut [Object]
             player.attack(monster, game);
         }
         None => {
-            move_by(id, dx, dy, &mut game.map, objects);
+            if move_by(id, dx, dy, &mut game.map, objects) {
+                enter_tile(id, objects, game, fov);
+                lose_item_in_water(objects, game);
+            }
         }
     }
 }
 
-pub fn move_towards(id: usize, target_x: i32, target_y: i32, map: &Map, objects: &mut [Object]) {
+pub fn move_towards(
+    id: usize,
+    target_x: i32,
+    target_y: i32,
+    map: &mut Map,
+    objects: &mut [Object],
+) -> bool {
     let dx = target_x - objects[id].x;
     let dy = target_y - objects[id].y;
     let distance = ((dx.pow(2) + dy.pow(2)) as f32).sqrt();
 
     let dx = (dx as f32 / distance).round() as i32;
     let dy = (dy as f32 / distance).round() as i32;
-    move_by(id, dx, dy, map, objects);
+    move_by(id, dx, dy, map, objects)
 }
 
 pub fn ai_take_turn(monster_id: usize, objects: &mut [Object], game: &mut Game, fov_map: &Fov) {
@@ -292,9 +538,18 @@ pub fn ai_take_turn(monster_id: usize, objects: &mut [Object], game: &mut Game,
             Confused {
                 previous_ai,
                 num_turns,
-            } => ai_confused(monster_id, objects, previous_ai, num_turns, game),
+            } => ai_confused(monster_id, objects, previous_ai, num_turns, fov_map, game),
+            Alerted {
+                x,
+                y,
+                previous_ai,
+                num_turns,
+            } => ai_alerted(monster_id, (x, y), objects, previous_ai, num_turns, fov_map, game),
         };
-        objects[monster_id].ai = Some(new_ai);
+        // Monsters can die on their own turn, e.g. by walking into lava.
+        if objects[monster_id].alive {
+            objects[monster_id].ai = Some(new_ai);
+        }
     }
 }
 
@@ -303,7 +558,9 @@ pub fn ai_basic(monster_id: usize, objects: &mut [Object], fov_map: &Fov, game:
     if fov_map.is_in_fov(monster_x, monster_y) {
         if objects[monster_id].distance_to(&objects[PLAYER]) >= 2.0 {
             let (player_x, player_y) = objects[PLAYER].pos();
-            move_towards(monster_id, player_x, player_y, &mut game.map, objects);
+            if move_towards(monster_id, player_x, player_y, &mut game.map, objects) {
+                enter_tile(monster_id, objects, game, fov_map);
+            }
         } else if objects[PLAYER].fighter.map_or(false, |f| f.hp > 0) {
             let (monster, player) = mut_two(monster_id, PLAYER, objects);
             monster.attack(player, game);
@@ -317,29 +574,57 @@ pub fn ai_confused(
     objects: &mut [Object],
     previous_ai: Box<Ai>,
     num_turns: i32,
+    fov_map: &Fov,
     game: &mut Game,
 ) -> Ai {
     if num_turns >= 0 {
-        move_by(
+        let moved = move_by(
             monster_id,
-            rand::thread_rng().gen_range(-1, 2),
-            rand::thread_rng().gen_range(-1, 2),
+            random::rng().gen_range(-1, 2),
+            random::rng().gen_range(-1, 2),
             &mut game.map,
             objects,
         );
+        if moved {
+            enter_tile(monster_id, objects, game, fov_map);
+        }
         Ai::Confused {
             previous_ai: previous_ai,
             num_turns: num_turns - 1,
         }
     } else {
-        game.log.add(
-            format!("The {} is no longer confused!", objects[monster_id].name),
-            colors::RED,
-        );
+        game.events.emit(GameEvent::ConfusionEnded {
+            name: objects[monster_id].name.clone(),
+        });
         *previous_ai
     }
 }
 
+pub fn ai_alerted(
+    monster_id: usize,
+    (target_x, target_y): (i32, i32),
+    objects: &mut [Object],
+    previous_ai: Box<Ai>,
+    num_turns: i32,
+    fov_map: &Fov,
+    game: &mut Game,
+) -> Ai {
+    let (monster_x, monster_y) = objects[monster_id].pos();
+    let arrived = (monster_x, monster_y) == (target_x, target_y);
+    if num_turns <= 0 || arrived || fov_map.is_in_fov(monster_x, monster_y) {
+        return *previous_ai;
+    }
+    if move_towards(monster_id, target_x, target_y, &mut game.map, objects) {
+        enter_tile(monster_id, objects, game, fov_map);
+    }
+    Ai::Alerted {
+        x: target_x,
+        y: target_y,
+        previous_ai: previous_ai,
+        num_turns: num_turns - 1,
+    }
+}
+
 /// Mutably borrow two *separate* elements from the given slice.
 /// Panics when the indexes are equal or out of bounds.
 fn mut_two<T>(first_index: usize, second_index: usize, items: &mut [T]) -> (&mut T, &mut T) {
@@ -353,21 +638,18 @@ fn mut_two<T>(first_index: usize, second_index: usize, items: &mut [T]) -> (&mut
     }
 }
 
-fn player_death(player: &mut Object, log: &mut Messages) {
-    log.add("You died!", colors::RED);
+fn player_death(player: &mut Object, events: &mut EventBus) {
+    events.emit(GameEvent::PlayerDied);
     player.char = '%';
     player.color = colors::DARK_RED;
 }
 
-fn monster_death(monster: &mut Object, log: &mut Messages) {
-    log.add(
-        format!(
-            "{} is dead! You gain {} experience points.",
-            monster.name,
-            monster.fighter.unwrap().xp
-        ),
-        colors::ORANGE,
-    );
+fn monster_death(monster: &mut Object, events: &mut EventBus) {
+    events.emit(GameEvent::MonsterDied {
+        name: monster.name.clone(),
+        x: monster.x,
+        y: monster.y,
+    });
     monster.char = '%';
     monster.color = colors::DARK_RED;
     monster.blocks = false;
@@ -0,0 +1,33 @@
+use rand::{Rng, SeedableRng, StdRng};
+
+use std::cell::RefCell;
+
+thread_local! {
+    static RNG: RefCell<StdRng> =
+        RefCell::new(StdRng::new().expect("could not seed the random number generator"));
+}
+
+/// Handle to the game's random number generator.
+/// Everything random in the game goes through it, so a seed reproduces levels exactly.
+#[derive(Clone, Copy, Debug)]
+pub struct GameRng;
+
+impl Rng for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        RNG.with(|rng| rng.borrow_mut().next_u32())
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        RNG.with(|rng| rng.borrow_mut().next_u64())
+    }
+}
+
+pub fn rng() -> GameRng {
+    GameRng
+}
+
+/// Restarts the generator from a fixed seed.
+pub fn seed(seed: u64) {
+    let key = [seed as usize, (seed >> 32) as usize];
+    RNG.with(|rng| *rng.borrow_mut() = StdRng::from_seed(&key[..]));
+}
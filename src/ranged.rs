@@ -0,0 +1,179 @@
+use item::{get_equipment_in_slot, is_stuck, take_from_inventory, target_tile, Slot};
+use object::*;
+use event::GameEvent;
+use ::*;
+
+use std::cmp;
+
+/// Farthest an item can be thrown by hand.
+const THROW_RANGE: i32 = 6;
+/// Damage done by thrown items that aren't made for it, like potions.
+const IMPROVISED_DAMAGE: i32 = 1;
+
+/// Throws one item of an inventory entry at a tile picked by the player.
+/// Returns whether the item was thrown.
+pub fn throw_item(
+    inventory_id: usize,
+    objects: &mut Vec<Object>,
+    ui: &mut Ui,
+    game: &mut Game,
+) -> bool {
+    if is_stuck(&game.inventory[inventory_id], &game.identification, &mut game.log) {
+        return false;
+    }
+    let name = game.identification.name_of(&game.inventory[inventory_id]);
+    game.log.add(
+        format!(
+            "Left-click a target tile to throw the {}, or right-click to cancel.",
+            name
+        ),
+        colors::LIGHT_CYAN,
+    );
+    let target = match pick_target(THROW_RANGE, objects, ui, game) {
+        Some(target) => target,
+        None => return false,
+    };
+
+    let mut item = take_from_inventory(&mut game.inventory, inventory_id, 1);
+    if item.equipment.is_some() {
+        item.unequip(&mut game.log);
+    }
+    let damage = thrown_damage(&item);
+    fly(item, damage, target, THROW_RANGE, objects, game);
+    true
+}
+
+/// Fires ammunition from the inventory with the launcher in the ranged slot.
+/// Returns whether anything was fired.
+pub fn fire(objects: &mut Vec<Object>, ui: &mut Ui, game: &mut Game) -> bool {
+    let launcher = get_equipment_in_slot(Slot::Ranged, &game.inventory)
+        .and_then(|id| game.inventory[id].equipment)
+        .and_then(|equipment| {
+            equipment
+                .launcher
+                .map(|launcher| (launcher, equipment.enchantment))
+        });
+    let (launcher, enchantment) = match launcher {
+        Some(launcher) => launcher,
+        None => {
+            game.log.add("You have nothing to fire with.", colors::WHITE);
+            return false;
+        }
+    };
+    let ammo_id = game.inventory
+        .iter()
+        .position(|item| item.missile.map_or(false, |m| m.ammo == Some(launcher.ammo)));
+    let ammo_id = match ammo_id {
+        Some(ammo_id) => ammo_id,
+        None => {
+            game.log.add("You are out of ammunition.", colors::RED);
+            return false;
+        }
+    };
+
+    game.log.add(
+        "Left-click a target tile to fire at, or right-click to cancel.",
+        colors::LIGHT_CYAN,
+    );
+    let target = match pick_target(launcher.range, objects, ui, game) {
+        Some(target) => target,
+        None => return false,
+    };
+    let ammo = take_from_inventory(&mut game.inventory, ammo_id, 1);
+    let damage = thrown_damage(&ammo) + launcher.damage + enchantment;
+    fly(ammo, damage, target, launcher.range, objects, game);
+    true
+}
+
+/// A tile in range other than the player's own.
+fn pick_target(
+    range: i32,
+    objects: &[Object],
+    ui: &mut Ui,
+    game: &mut Game,
+) -> Option<(i32, i32)> {
+    match target_tile(ui, objects, game, Some(range as f32)) {
+        Some(target) if target != objects[PLAYER].pos() => Some(target),
+        _ => None,
+    }
+}
+
+fn thrown_damage(item: &Object) -> i32 {
+    match (item.missile, item.equipment) {
+        (Some(missile), _) => missile.damage,
+        (None, Some(equipment)) => cmp::max(IMPROVISED_DAMAGE, equipment.power()),
+        (None, None) => IMPROVISED_DAMAGE,
+    }
+}
+
+/// Sends an item from the player along the line through the target, until it hits a monster
+/// or a wall or runs out of range. The item is left on the floor where it stopped.
+fn fly(
+    mut missile: Object,
+    damage: i32,
+    target: (i32, i32),
+    range: i32,
+    objects: &mut Vec<Object>,
+    game: &mut Game,
+) {
+    let start = objects[PLAYER].pos();
+    let mut landing = start;
+    for (x, y) in line(start, target, range) {
+        let blocked = game.map
+            .get(x, y)
+            .map_or(true, |tile| tile.kind.blocks_movement());
+        if blocked || objects[PLAYER].distance(x, y) > range as f32 {
+            break;
+        }
+        landing = (x, y);
+
+        let monster_id = objects
+            .iter()
+            .position(|object| object.pos() == (x, y) && object.fighter.is_some() && object.alive);
+        if let Some(monster_id) = monster_id {
+            hit(&missile, damage, monster_id, objects, game);
+            break;
+        }
+    }
+
+    // Whatever comes down in lava or deep water is gone for good.
+    let kind = game.map[landing].kind;
+    if kind.is_hazard() {
+        game.events.emit(GameEvent::MissileLost {
+            missile: game.identification.name_of(&missile),
+            lava: kind == TileKind::Lava,
+        });
+        return;
+    }
+    missile.set_pos(landing.0, landing.1);
+    objects.push(missile);
+}
+
+fn hit(missile: &Object, damage: i32, target_id: usize, objects: &mut [Object], game: &mut Game) {
+    let target = &mut objects[target_id];
+    let damage = target.resisted_damage(damage - target.defense(game), DamageType::Physical, game);
+    game.events.emit(GameEvent::MissileHit {
+        missile: game.identification.name_of(missile),
+        target: target.name.clone(),
+        damage: damage,
+    });
+    if let Some(xp) = target.take_damage(damage, &mut game.events) {
+        game.events.emit(GameEvent::XpGained { amount: xp });
+    }
+}
+
+/// Tiles along the line from one position through another, starting next to the first.
+/// The line is `length` tiles long along its longer axis.
+fn line((x, y): (i32, i32), (target_x, target_y): (i32, i32), length: i32) -> Vec<(i32, i32)> {
+    let (dx, dy) = (target_x - x, target_y - y);
+    let steps = cmp::max(dx.abs(), dy.abs()) as f32;
+    (1..length + 1)
+        .map(|step| {
+            let t = step as f32 / steps;
+            (
+                x + (dx as f32 * t).round() as i32,
+                y + (dy as f32 * t).round() as i32,
+            )
+        })
+        .collect()
+}
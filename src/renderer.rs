@@ -1,11 +1,10 @@
-use map::*;
 use object::*;
+use trap::TRAP_GLYPH;
+use camera::Camera;
+use identify::Identification;
 use ::*;
 
-const COLOR_DARK_WALL: [f32; 4] = [0.0,0.0, 0.39, 1.0];
-const COLOR_LIGHT_WALL: [f32; 4] = [0.51, 0.43, 0.2, 1.0];
-const COLOR_DARK_GROUND: [f32; 4] = [0.2, 0.2, 0.59, 1.0];
-const COLOR_LIGHT_GROUND: [f32; 4] = [0.78, 0.7, 0.2, 1.0];
+use std::cmp;
 
 const TORCH_RADIUS: i32 = 10;
 
@@ -18,32 +17,50 @@ const MSG_WIDTH: i32 = SCREEN_WIDTH - BAR_WIDTH - 2;
 const MSG_X: i32 = BAR_WIDTH + 2;
 
 pub const INVENTORY_WIDTH: i32 = 50;
+const QUANTITY_PROMPT_WIDTH: i32 = 40;
+/// Lines of options and section titles on one page of a menu. Each page needs its own letters.
+const MENU_PAGE_LINES: usize = 26;
+
+/// Size of the part of the screen that shows the map.
+pub const VIEW_WIDTH: i32 = SCREEN_WIDTH;
+pub const VIEW_HEIGHT: i32 = SCREEN_HEIGHT - PANEL_HEIGHT;
 
 pub fn render_all(ui: &mut Ui, objects: &[Object], game: &mut Game, fov_recompute: bool) {
     // TODO: Make render not take mutable references.
     if fov_recompute {
         let player = &objects[PLAYER];
+        ui.fov.refresh(&game.map);
         ui.fov.recompute(player.x, player.y, TORCH_RADIUS);
+    }
+    ui.camera
+        .center_on(objects[PLAYER].x, objects[PLAYER].y, &game.map);
 
-        for y in 0..MAP_HEIGHT {
-            for x in 0..MAP_WIDTH {
-                let visible = ui.fov.is_in_fov(x, y);
-                let wall = game.map[x as usize][y as usize].block_sight;
-                let color = match (visible, wall) {
-                    (false, true) => COLOR_DARK_WALL,
-                    (false, false) => COLOR_DARK_GROUND,
-                    (true, true) => COLOR_LIGHT_WALL,
-                    (true, false) => COLOR_LIGHT_GROUND,
-                };
-
-                let explored = &mut game.map[x as usize][y as usize].explored;
-                if visible {
-                    *explored = true;
-                }
-                if *explored {
+    for screen_y in 0..VIEW_HEIGHT {
+        for screen_x in 0..VIEW_WIDTH {
+            let (x, y) = ui.camera.to_map(screen_x, screen_y).unwrap();
+            let visible = ui.fov.is_in_fov(x, y);
+            let tile = match game.map.get_mut(x, y) {
+                Some(tile) => tile,
+                None => {
                     ui.con
-                        .set_char_background(x, y, color, BackgroundFlag::Set);
+                        .put_char_ex(screen_x, screen_y, ' ', colors::BLACK, colors::BLACK);
+                    continue;
                 }
+            };
+            if visible {
+                tile.explored = true;
+            }
+            if tile.explored {
+                let (foreground, background) = tile.kind.colors(visible);
+                let (glyph, foreground) = match tile.trap {
+                    Some(trap) if !trap.hidden => (TRAP_GLYPH, trap.kind.color()),
+                    _ => (tile.kind.glyph(), foreground),
+                };
+                ui.con
+                    .put_char_ex(screen_x, screen_y, glyph, foreground, background);
+            } else {
+                ui.con
+                    .put_char_ex(screen_x, screen_y, ' ', colors::BLACK, colors::BLACK);
             }
         }
     }
@@ -52,18 +69,18 @@ pub fn render_all(ui: &mut Ui, objects: &[Object], game: &mut Game, fov_recomput
         .iter()
         .filter(|o| {
             ui.fov.is_in_fov(o.x, o.y)
-                || (o.always_visible && game.map[o.x as usize][o.y as usize].explored)
+                || (o.always_visible && game.map[(o.x, o.y)].explored)
         })
         .collect();
     to_draw.sort_by(|o1, o2| o1.blocks.cmp(&o2.blocks));
     for object in &to_draw {
-        object.draw(&mut ui.con);
+        object.draw(&mut ui.con, &ui.camera, game.identification.color_of(object));
     }
 
     blit(
         &ui.con,
         (0, 0),
-        (MAP_WIDTH, MAP_HEIGHT),
+        (VIEW_WIDTH, VIEW_HEIGHT),
         &mut ui.root,
         (0, 0),
         1.0,
@@ -115,7 +132,13 @@ pub fn render_all(ui: &mut Ui, objects: &[Object], game: &mut Game, fov_recomput
         0,
         BackgroundFlag::None,
         TextAlignment::Left,
-        get_names_under_mouse(ui.mouse, objects, &ui.fov),
+        get_names_under_mouse(
+            ui.mouse,
+            objects,
+            &ui.fov,
+            &ui.camera,
+            &game.identification,
+        ),
     );
 
     blit(
@@ -164,34 +187,177 @@ fn render_bar(
     );
 }
 
-fn get_names_under_mouse(mouse: Mouse, objects: &[Object], fov: &Fov) -> String {
-    let (x, y) = (mouse.cx as i32, mouse.cy as i32);
+fn get_names_under_mouse(
+    mouse: Mouse,
+    objects: &[Object],
+    fov: &Fov,
+    camera: &Camera,
+    identification: &Identification,
+) -> String {
+    let (x, y) = match camera.to_map(mouse.cx as i32, mouse.cy as i32) {
+        Some(position) => position,
+        None => return String::new(),
+    };
     objects
         .iter()
         .filter(|obj| obj.pos() == (x, y) && fov.is_in_fov(obj.x, obj.y))
-        .map(|obj| obj.name.clone())
+        .map(|obj| identification.name_of(obj))
         .collect::<Vec<_>>()
         .join(", ")
 }
 
+/// A group of menu options listed under a title. Each option carries the id returned when it
+/// is picked, and the colour it is shown in.
+pub struct MenuSection<T> {
+    pub title: String,
+    pub options: Vec<(usize, T, [f32; 4])>,
+}
+
+enum MenuLine<'a> {
+    Title(&'a str),
+    Option(usize, &'a str, [f32; 4]),
+}
+
 pub fn menu<T: AsRef<str>>(
     header: &str,
     options: &[T],
     width: i32,
     root: &mut Console,
 ) -> Option<usize> {
-    assert!(
-        options.len() <= 26,
-        "Cannot have a menu with more than 26 options."
-    );
+    let section = MenuSection {
+        title: String::new(),
+        options: options
+            .iter()
+            .enumerate()
+            .map(|(index, option)| (index, option.as_ref(), colors::WHITE))
+            .collect(),
+    };
+    sectioned_menu(header, &[section], width, root)
+}
+
+/// Shows a menu of options grouped in sections, and returns the id of the picked option.
+/// Options are picked by their letter, or with the arrow keys and Enter. Long menus are split
+/// into pages, and Tab shows one section at a time.
+pub fn sectioned_menu<T: AsRef<str>>(
+    header: &str,
+    sections: &[MenuSection<T>],
+    width: i32,
+    root: &mut Console,
+) -> Option<usize> {
+    use ui::input::KeyCode;
+
+    let sections: Vec<&MenuSection<T>> = sections
+        .iter()
+        .filter(|section| !section.options.is_empty())
+        .collect();
+    let mut filter: Option<usize> = None;
+    let mut page = 0;
+    let mut cursor = 0;
+    loop {
+        let shown: Vec<&MenuSection<T>> = match filter {
+            Some(index) => vec![sections[index]],
+            None => sections.clone(),
+        };
+        let pages = paginate(&shown);
+        page = cmp::min(page, pages.len() - 1);
+        let choices: Vec<usize> = pages[page]
+            .iter()
+            .filter_map(|line| match *line {
+                MenuLine::Option(id, _, _) => Some(id),
+                MenuLine::Title(_) => None,
+            })
+            .collect();
+        cursor = cmp::min(cursor, choices.len().saturating_sub(1));
+
+        let mut footer = vec![];
+        if pages.len() > 1 {
+            footer.push(format!("Page {}/{}, arrows to turn", page + 1, pages.len()));
+        }
+        if sections.len() > 1 {
+            footer.push("Tab to filter".to_string());
+        }
+        draw_menu(header, &pages[page], cursor, &footer.join(", "), width, root);
+
+        let key = root.wait_for_keypress(true);
+        match key.code {
+            KeyCode::Up if cursor > 0 => cursor -= 1,
+            KeyCode::Up if page > 0 => {
+                page -= 1;
+                cursor = usize::max_value();
+            }
+            KeyCode::Down if cursor + 1 < choices.len() => cursor += 1,
+            KeyCode::Down | KeyCode::Right | KeyCode::PageDown if page + 1 < pages.len() => {
+                page += 1;
+                cursor = 0;
+            }
+            KeyCode::Left | KeyCode::PageUp if page > 0 => {
+                page -= 1;
+                cursor = 0;
+            }
+            KeyCode::Up
+            | KeyCode::Down
+            | KeyCode::Left
+            | KeyCode::Right
+            | KeyCode::PageUp
+            | KeyCode::PageDown if !choices.is_empty() => {}
+            KeyCode::Tab if sections.len() > 1 => {
+                filter = match filter {
+                    None => Some(0),
+                    Some(index) if index + 1 < sections.len() => Some(index + 1),
+                    Some(_) => None,
+                };
+                page = 0;
+                cursor = 0;
+            }
+            KeyCode::Enter => return choices.get(cursor).cloned(),
+            _ if key.printable.is_alphabetic() => {
+                let index = key.printable.to_ascii_lowercase() as usize - 'a' as usize;
+                return choices.get(index).cloned();
+            }
+            _ => return None,
+        }
+    }
+}
 
+/// Splits the sections into pages, never leaving a title alone at the bottom of a page.
+fn paginate<'a, T: AsRef<str>>(sections: &[&'a MenuSection<T>]) -> Vec<Vec<MenuLine<'a>>> {
+    let mut pages = vec![vec![]];
+    for section in sections {
+        if !section.title.is_empty() {
+            if pages.last().unwrap().len() + 1 >= MENU_PAGE_LINES {
+                pages.push(vec![]);
+            }
+            pages.last_mut().unwrap().push(MenuLine::Title(&section.title));
+        }
+        for &(id, ref text, color) in &section.options {
+            if pages.last().unwrap().len() >= MENU_PAGE_LINES {
+                pages.push(vec![]);
+            }
+            pages
+                .last_mut()
+                .unwrap()
+                .push(MenuLine::Option(id, text.as_ref(), color));
+        }
+    }
+    pages
+}
+
+fn draw_menu(
+    header: &str,
+    lines: &[MenuLine],
+    cursor: usize,
+    footer: &str,
+    width: i32,
+    root: &mut Console,
+) {
     // Calculate total height for header and contents
     let header_height = if header.is_empty() {
         0
     } else {
         root.get_height_rect(0, 0, width, SCREEN_HEIGHT, header)
     };
-    let height = options.len() as i32 + header_height;
+    let footer_height = if footer.is_empty() { 0 } else { 1 };
+    let height = lines.len() as i32 + header_height + footer_height;
 
     let mut window = Console::new(width, height);
     window.set_default_foreground(colors::WHITE);
@@ -205,40 +371,94 @@ pub fn menu<T: AsRef<str>>(
         header,
     );
 
-    for (index, option_text) in options.iter().enumerate() {
-        let menu_letter = (b'a' + index as u8) as char;
-        let text = format!("({}) {}", menu_letter, option_text.as_ref());
+    let mut option_index = 0;
+    for (row, line) in lines.iter().enumerate() {
+        let text = match *line {
+            MenuLine::Title(title) => {
+                window.set_default_foreground(colors::LIGHT_YELLOW);
+                title.to_string()
+            }
+            MenuLine::Option(_, option_text, color) => {
+                let menu_letter = (b'a' + option_index as u8) as char;
+                let marker = if option_index == cursor { '>' } else { ' ' };
+                option_index += 1;
+                window.set_default_foreground(color);
+                format!("{}({}) {}", marker, menu_letter, option_text)
+            }
+        };
         window.print_ex(
             0,
-            header_height + index as i32,
+            header_height + row as i32,
             BackgroundFlag::None,
             TextAlignment::Left,
             text,
         );
     }
+    if footer_height > 0 {
+        window.set_default_foreground(colors::LIGHT_GREY);
+        window.print_ex(
+            0,
+            height - 1,
+            BackgroundFlag::None,
+            TextAlignment::Left,
+            footer,
+        );
+    }
 
     let x = SCREEN_WIDTH / 2 - width / 2;
     let y = SCREEN_HEIGHT / 2 - height / 2;
     blit(
         &window,
         (0, 0),
-        (MAP_WIDTH, MAP_HEIGHT),
+        (width, height),
         root,
         (x, y),
         1.0,
         0.7,
     );
-
     root.flush();
-    let key = root.wait_for_keypress(true);
-    if key.printable.is_alphabetic() {
-        let index = key.printable.to_ascii_lowercase() as usize - 'a' as usize;
-        if index < options.len() {
-            Some(index)
-        } else {
-            None
+}
+
+/// Asks for a number from 1 to `max`. Enter on an empty prompt picks `max`, Escape cancels.
+pub fn quantity_prompt(header: &str, max: u32, root: &mut Console) -> Option<u32> {
+    use ui::input::KeyCode::{Backspace, Enter, Escape};
+    let mut input = String::new();
+    loop {
+        let mut window = Console::new(QUANTITY_PROMPT_WIDTH, 1);
+        window.set_default_foreground(colors::WHITE);
+        window.print_ex(
+            0,
+            0,
+            BackgroundFlag::None,
+            TextAlignment::Left,
+            format!("{} (1-{}): {}_", header, max, input),
+        );
+        blit(
+            &window,
+            (0, 0),
+            (QUANTITY_PROMPT_WIDTH, 1),
+            root,
+            (SCREEN_WIDTH / 2 - QUANTITY_PROMPT_WIDTH / 2, SCREEN_HEIGHT / 2),
+            1.0,
+            0.7,
+        );
+        root.flush();
+
+        let key = root.wait_for_keypress(true);
+        match key.code {
+            Enter if input.is_empty() => return Some(max),
+            Enter => {
+                return match input.parse::<u32>() {
+                    Ok(0) | Err(_) => None,
+                    Ok(count) => Some(cmp::min(count, max)),
+                }
+            }
+            Escape => return None,
+            Backspace => {
+                input.pop();
+            }
+            _ if key.printable.is_digit(10) && input.len() < 6 => input.push(key.printable),
+            _ => {}
         }
-    } else {
-        None
     }
 }
@@ -0,0 +1,223 @@
+use rand::Rng;
+use rand::distributions::{IndependentSample, Weighted, WeightedChoice};
+
+/// A value that takes effect from the given dungeon level onwards.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Transition {
+    pub level: u32,
+    pub value: u32,
+}
+
+/// How a curve behaves between two of its transitions.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Interpolation {
+    /// Keeps the value of the last transition until the next one is reached.
+    Step,
+    /// Moves linearly from one transition to the next.
+    Linear,
+}
+
+/// Rarity tiers scale an entry's weight down relative to common entries.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Rarity {
+    Common,
+    Uncommon,
+    Rare,
+    VeryRare,
+}
+
+/// How often and where something is spawned, as defined in the data files.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpawnRule {
+    pub weights: Vec<Transition>,
+    pub interpolation: Interpolation,
+    pub rarity: Rarity,
+    /// Dungeon levels where at least one is always placed.
+    pub guaranteed: Vec<u32>,
+}
+
+/// Something that can be picked from a spawn table.
+pub trait Spawnable {
+    fn name(&self) -> &str;
+    fn spawn_rule(&self) -> &SpawnRule;
+}
+
+impl<'a, T: Spawnable> Spawnable for &'a T {
+    fn name(&self) -> &str {
+        (*self).name()
+    }
+
+    fn spawn_rule(&self) -> &SpawnRule {
+        (*self).spawn_rule()
+    }
+}
+
+/// Weighted random selection over a set of spawnable entries.
+pub struct SpawnTable<'a, T: 'a> {
+    entries: &'a [T],
+    out_of_depth_chance: f32,
+    out_of_depth_levels: u32,
+}
+
+impl Default for Interpolation {
+    fn default() -> Self {
+        Interpolation::Step
+    }
+}
+
+impl Default for Rarity {
+    fn default() -> Self {
+        Rarity::Common
+    }
+}
+
+impl Rarity {
+    /// Percentage of the curve weight that is kept for this tier.
+    pub fn percent(self) -> u32 {
+        match self {
+            Rarity::Common => 100,
+            Rarity::Uncommon => 40,
+            Rarity::Rare => 15,
+            Rarity::VeryRare => 5,
+        }
+    }
+}
+
+impl SpawnRule {
+    pub fn weight(&self, level: u32) -> u32 {
+        value_at(&self.weights, self.interpolation, level) * self.rarity.percent() / 100
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.weights.windows(2).any(|w| w[0].level >= w[1].level) {
+            return Err("spawn weight levels must be strictly increasing".into());
+        }
+        Ok(())
+    }
+}
+
+impl<'a, T: Spawnable> SpawnTable<'a, T> {
+    pub fn new(entries: &'a [T]) -> Self {
+        SpawnTable {
+            entries: entries,
+            out_of_depth_chance: 0.0,
+            out_of_depth_levels: 0,
+        }
+    }
+
+    /// Makes a share of the rolls use the table of a deeper dungeon level.
+    pub fn with_out_of_depth(mut self, chance: f32, levels: u32) -> Self {
+        self.out_of_depth_chance = chance;
+        self.out_of_depth_levels = levels;
+        self
+    }
+
+    /// Picks a random entry, or `None` if nothing can spawn on this level.
+    pub fn roll<R: Rng>(&self, level: u32, rng: &mut R) -> Option<&'a T> {
+        let level = if rng.next_f32() < self.out_of_depth_chance {
+            level + self.out_of_depth_levels
+        } else {
+            level
+        };
+        let mut chances: Vec<_> = self.entries
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| Weighted {
+                weight: entry.spawn_rule().weight(level),
+                item: index,
+            })
+            .filter(|chance| chance.weight > 0)
+            .collect();
+        if chances.is_empty() {
+            return None;
+        }
+        let choice = WeightedChoice::new(&mut chances);
+        Some(&self.entries[choice.ind_sample(rng)])
+    }
+
+    /// Entries that must appear at least once on the given level.
+    pub fn guaranteed(&self, level: u32) -> Vec<&'a T> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.spawn_rule().guaranteed.contains(&level))
+            .collect()
+    }
+
+    /// The chance of each entry being picked by a single roll, out-of-depth rolls included.
+    pub fn probabilities(&self, level: u32) -> Vec<(&'a T, f32)> {
+        let normal = self.level_probabilities(level);
+        let deep = self.level_probabilities(level + self.out_of_depth_levels);
+        self.entries
+            .iter()
+            .zip(normal.iter().zip(deep.iter()))
+            .map(|(entry, (normal, deep))| {
+                let chance = self.out_of_depth_chance;
+                (entry, normal * (1.0 - chance) + deep * chance)
+            })
+            .collect()
+    }
+
+    /// Human readable listing of the resolved probabilities on a level.
+    pub fn describe(&self, level: u32) -> String {
+        let guaranteed = self.guaranteed(level);
+        self.probabilities(level)
+            .iter()
+            .map(|&(entry, chance)| {
+                let mut line = format!("  {:<28} {:>6.2}%", entry.name(), chance * 100.0);
+                if guaranteed.iter().any(|g| g.name() == entry.name()) {
+                    line.push_str("  (guaranteed)");
+                }
+                line
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn level_probabilities(&self, level: u32) -> Vec<f32> {
+        let weights: Vec<u32> = self.entries
+            .iter()
+            .map(|entry| entry.spawn_rule().weight(level))
+            .collect();
+        let total: u32 = weights.iter().sum();
+        weights
+            .iter()
+            .map(|&weight| {
+                if total == 0 {
+                    0.0
+                } else {
+                    weight as f32 / total as f32
+                }
+            })
+            .collect()
+    }
+}
+
+/// Evaluates a curve of transitions at a dungeon level.
+/// Levels before the first transition have a value of zero.
+pub fn value_at(table: &[Transition], interpolation: Interpolation, level: u32) -> u32 {
+    match interpolation {
+        Interpolation::Step => from_dungeon_level(table, level),
+        Interpolation::Linear => {
+            let next = table.iter().position(|transition| transition.level > level);
+            match next {
+                Some(0) => 0,
+                Some(next) => {
+                    let (from, to) = (table[next - 1], table[next]);
+                    let progress = (level - from.level) as f32 / (to.level - from.level) as f32;
+                    let value = from.value as f32 + (to.value as f32 - from.value as f32) * progress;
+                    value.round() as u32
+                }
+                None => table.last().map_or(0, |transition| transition.value),
+            }
+        }
+    }
+}
+
+fn from_dungeon_level(table: &[Transition], level: u32) -> u32 {
+    table
+        .iter()
+        .rev()
+        .find(|transition| level >= transition.level)
+        .map_or(0, |transition| transition.value)
+}
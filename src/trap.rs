@@ -0,0 +1,181 @@
+use map::*;
+use object::*;
+use event::GameEvent;
+use ::*;
+
+use rand::Rng;
+use random;
+use std::cmp;
+
+/// Traps are drawn with this glyph once they have been found.
+pub const TRAP_GLYPH: char = '^';
+
+const PIT_DAMAGE: i32 = 8;
+const DART_DAMAGE: i32 = 6;
+const ALARM_RADIUS: f32 = 15.0;
+const ALARM_TURNS: i32 = 20;
+const TELEPORT_ATTEMPTS: i32 = 200;
+/// Chance per step of noticing each hidden trap next to the player.
+const PERCEPTION_CHANCE: f32 = 0.1;
+const SEARCH_RADIUS: i32 = 2;
+const SEARCH_CHANCE: f32 = 0.6;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum TrapKind {
+    Pit,
+    Dart,
+    Teleport,
+    Alarm,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Trap {
+    pub kind: TrapKind,
+    /// Hidden traps are not drawn and are sprung by the player without warning.
+    pub hidden: bool,
+}
+
+impl std::fmt::Display for TrapKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            TrapKind::Pit => write!(f, "pit trap"),
+            TrapKind::Dart => write!(f, "dart trap"),
+            TrapKind::Teleport => write!(f, "teleport trap"),
+            TrapKind::Alarm => write!(f, "alarm trap"),
+        }
+    }
+}
+
+impl TrapKind {
+    pub fn random() -> TrapKind {
+        *random::rng()
+            .choose(&[
+                TrapKind::Pit,
+                TrapKind::Dart,
+                TrapKind::Teleport,
+                TrapKind::Alarm,
+            ])
+            .unwrap()
+    }
+
+    pub fn color(self) -> [f32; 4] {
+        match self {
+            TrapKind::Pit => colors::DARK_RED,
+            TrapKind::Dart => colors::LIGHT_GREEN,
+            TrapKind::Teleport => colors::LIGHT_VIOLET,
+            TrapKind::Alarm => colors::YELLOW,
+        }
+    }
+}
+
+impl Trap {
+    pub fn new(kind: TrapKind) -> Self {
+        Trap {
+            kind: kind,
+            hidden: true,
+        }
+    }
+}
+
+/// Springs the trap under an object, if there is one.
+/// The trap is revealed afterwards if the player saw it go off.
+pub fn trigger_trap(id: usize, objects: &mut [Object], game: &mut Game, fov: &Fov) {
+    let (x, y) = objects[id].pos();
+    let seen = id == PLAYER || fov.is_in_fov(x, y);
+    let kind = match game.map[(x, y)].trap.as_mut() {
+        Some(trap) => {
+            if seen {
+                trap.hidden = false;
+            }
+            trap.kind
+        }
+        None => return,
+    };
+    if seen {
+        game.events.emit(GameEvent::TrapTriggered {
+            name: objects[id].name.clone(),
+            trap: kind,
+            player: id == PLAYER,
+        });
+    }
+
+    match kind {
+        TrapKind::Pit => hurt(id, PIT_DAMAGE, DamageType::Physical, objects, game),
+        TrapKind::Dart => hurt(id, DART_DAMAGE, DamageType::Poison, objects, game),
+        TrapKind::Teleport => teleport(id, objects, game),
+        TrapKind::Alarm => sound_alarm(x, y, objects),
+    }
+}
+
+/// Gives the player a chance to notice hidden traps right next to them.
+pub fn perception_check(objects: &[Object], game: &mut Game) {
+    for trap in reveal_traps(objects[PLAYER].pos(), 1, PERCEPTION_CHANCE, &mut game.map) {
+        game.events.emit(GameEvent::TrapFound {
+            trap: trap,
+            searching: false,
+        });
+    }
+}
+
+/// Spends a turn looking for hidden traps around the player.
+pub fn search(objects: &[Object], game: &mut Game) {
+    let found = reveal_traps(objects[PLAYER].pos(), SEARCH_RADIUS, SEARCH_CHANCE, &mut game.map);
+    if found.is_empty() {
+        game.events.emit(GameEvent::SearchFailed);
+    }
+    for trap in found {
+        game.events.emit(GameEvent::TrapFound {
+            trap: trap,
+            searching: true,
+        });
+    }
+}
+
+/// Reveals each hidden trap within a radius with the given chance,
+/// returning the kinds of the traps that were found.
+pub fn reveal_traps((x, y): (i32, i32), radius: i32, chance: f32, map: &mut Map) -> Vec<TrapKind> {
+    let mut found = vec![];
+    for trap_x in cmp::max(0, x - radius)..cmp::min(map.width(), x + radius + 1) {
+        for trap_y in cmp::max(0, y - radius)..cmp::min(map.height(), y + radius + 1) {
+            let tile = &mut map[(trap_x, trap_y)];
+            if let Some(ref mut trap) = tile.trap {
+                if trap.hidden && random::rng().next_f32() < chance {
+                    trap.hidden = false;
+                    tile.explored = true;
+                    found.push(trap.kind);
+                }
+            }
+        }
+    }
+    found
+}
+
+/// Moves an object to a random safe and unoccupied tile on the level.
+fn teleport(id: usize, objects: &mut [Object], game: &mut Game) {
+    for _ in 0..TELEPORT_ATTEMPTS {
+        let x = random::rng().gen_range(1, game.map.width() - 1);
+        let y = random::rng().gen_range(1, game.map.height() - 1);
+        let tile = &game.map[(x, y)];
+        if is_safe_floor(tile) && tile.trap.is_none() && !is_blocked(x, y, &game.map, objects) {
+            objects[id].set_pos(x, y);
+            return;
+        }
+    }
+}
+
+/// Sends every idle monster within earshot towards the alarm.
+fn sound_alarm(x: i32, y: i32, objects: &mut [Object]) {
+    for object in objects.iter_mut() {
+        if object.distance(x, y) > ALARM_RADIUS {
+            continue;
+        }
+        if let Some(Ai::Basic) = object.ai {
+            object.ai = Some(Ai::Alerted {
+                x: x,
+                y: y,
+                previous_ai: Box::new(Ai::Basic),
+                num_turns: ALARM_TURNS,
+            });
+        }
+    }
+}
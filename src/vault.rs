@@ -0,0 +1,132 @@
+use spawn::{SpawnRule, Spawnable};
+
+use rand::Rng;
+use random;
+use std::collections::HashMap;
+
+/// Layout character for a wall.
+pub const VAULT_WALL: char = '#';
+/// Layout character for an empty floor.
+pub const VAULT_FLOOR: char = '.';
+/// Layout character for a closed door.
+pub const VAULT_DOOR: char = '+';
+/// Layout character for tiles left untouched when the vault is stamped.
+pub const VAULT_KEEP: char = ' ';
+
+/// A fixed monster or item placed on a vault tile.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum VaultSpawn {
+    Monster(String),
+    Item(String),
+}
+
+/// The contents of `data/vaults.json`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct VaultFile {
+    /// Chance of a random vault on each level, on top of any guaranteed ones.
+    pub chance: f32,
+    pub vaults: Vec<VaultTemplate>,
+}
+
+/// A hand-authored room, drawn as ASCII rows.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct VaultTemplate {
+    pub name: String,
+    pub layout: Vec<String>,
+    /// Characters that place a monster or item on a floor tile.
+    #[serde(default)]
+    pub legend: HashMap<char, VaultSpawn>,
+    /// Whether the vault may be rotated by 90 degree steps.
+    #[serde(default)]
+    pub rotate: bool,
+    /// Whether the vault may be mirrored horizontally.
+    #[serde(default)]
+    pub mirror: bool,
+    #[serde(default)]
+    pub spawn: SpawnRule,
+}
+
+impl VaultTemplate {
+    /// The layout as a grid indexed by `[x][y]`, randomly rotated and mirrored if allowed.
+    pub fn random_orientation(&self) -> Vec<Vec<char>> {
+        let rows: Vec<Vec<char>> = self.layout.iter().map(|row| row.chars().collect()).collect();
+        let mut grid: Vec<Vec<char>> = (0..rows[0].len())
+            .map(|x| rows.iter().map(|row| row[x]).collect())
+            .collect();
+
+        if self.rotate {
+            for _ in 0..random::rng().gen_range(0, 4) {
+                grid = rotate(&grid);
+            }
+        }
+        if self.mirror && random::rng().gen() {
+            grid.reverse();
+        }
+        grid
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let width = self.layout.first().map_or(0, |row| row.chars().count());
+        if width == 0 {
+            return Err("layout must not be empty".into());
+        }
+        if self.layout.iter().any(|row| row.chars().count() != width) {
+            return Err("layout rows must all have the same length".into());
+        }
+        for (key, _) in &self.legend {
+            if [VAULT_WALL, VAULT_FLOOR, VAULT_DOOR, VAULT_KEEP].contains(key) {
+                return Err(format!("legend cannot redefine '{}'", key));
+            }
+        }
+
+        let height = self.layout.len();
+        let mut floor = 0;
+        let mut entrances = 0;
+        for (y, row) in self.layout.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                match c {
+                    VAULT_WALL | VAULT_KEEP => {}
+                    VAULT_FLOOR | VAULT_DOOR => floor += 1,
+                    c if self.legend.contains_key(&c) => floor += 1,
+                    c => return Err(format!("unknown layout character '{}'", c)),
+                }
+                // Untouched tiles on the edge may well be solid rock, so only floors
+                // and doors count as a way in.
+                let border = x == 0 || y == 0 || x == width - 1 || y == height - 1;
+                if border && c != VAULT_WALL && c != VAULT_KEEP {
+                    entrances += 1;
+                }
+            }
+        }
+        if floor == 0 {
+            return Err("layout must contain at least one floor tile".into());
+        }
+        if entrances == 0 {
+            return Err("layout needs an opening in its outer wall".into());
+        }
+        self.spawn.validate()
+    }
+}
+
+impl Spawnable for VaultTemplate {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn spawn_rule(&self) -> &SpawnRule {
+        &self.spawn
+    }
+}
+
+/// Rotates a `[x][y]` grid by 90 degrees clockwise.
+fn rotate(grid: &[Vec<char>]) -> Vec<Vec<char>> {
+    let width = grid.len();
+    let height = grid[0].len();
+    (0..height)
+        .map(|new_x| {
+            (0..width)
+                .map(|new_y| grid[new_y][height - 1 - new_x])
+                .collect()
+        })
+        .collect()
+}